@@ -0,0 +1,191 @@
+//! Finds the shortest solutions of levels
+//!
+//! `cargo run --release --bin solve -- <level>... [--max-states <n>]`,
+//! where a level is either its identifier or its number from the level label.
+//!
+//! With `--all` instead of levels every level of the campaign is checked,
+//! `--report <file>` writes the results as json and `--min-inputs <n>`
+//! treats levels solved in fewer inputs as broken.
+//! Exits with an error if any level is broken,
+//! levels without a goal or target are skipped as there is nothing to solve.
+
+use std::{path::PathBuf, time::Instant};
+
+use linksider::game::sim;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Status {
+    Solved,
+    Unsolvable,
+    /// Gave up after `--max-states`
+    Unknown,
+    /// Has no goal or target, like the title screen
+    NoObjective,
+}
+
+#[derive(Debug, Serialize)]
+struct LevelReport {
+    /// Number of the level, starting from 1 like in the level label
+    number: usize,
+    identifier: String,
+    status: Status,
+    inputs: Option<usize>,
+    solution: Option<Vec<String>>,
+    explored: usize,
+    seconds: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+    max_states: usize,
+    levels: Vec<LevelReport>,
+}
+
+fn main() {
+    let mut levels = Vec::new();
+    let mut all = false;
+    let mut max_states = 2_000_000;
+    let mut min_inputs = 0;
+    let mut report_path: Option<PathBuf> = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--all" => all = true,
+            "--max-states" => {
+                max_states = args
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .expect("--max-states expects a number");
+            }
+            "--min-inputs" => {
+                min_inputs = args
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .expect("--min-inputs expects a number");
+            }
+            "--report" => report_path = Some(args.next().expect("--report expects a file").into()),
+            _ => levels.push(arg),
+        }
+    }
+
+    sim::load_defs("assets").unwrap_or_else(|e| panic!("{e}"));
+    let project = sim::load_project("assets/world.ldtk").unwrap_or_else(|e| panic!("{e}"));
+    let all_levels: Vec<_> = sim::iter_levels(&project).collect();
+    let cells = sim::CellTable::from_ldtk(&project);
+    if levels.is_empty() && !all {
+        eprintln!("Usage: solve (<level>... | --all) [--max-states <n>] [--min-inputs <n>] [--report <file>]");
+        eprintln!("Levels:");
+        for (index, level) in all_levels.iter().enumerate() {
+            eprintln!("  {}: {}", index + 1, level.identifier);
+        }
+        std::process::exit(1);
+    }
+
+    let numbers: Vec<usize> = if all {
+        (1..=all_levels.len()).collect()
+    } else {
+        levels
+            .iter()
+            .map(|name| {
+                let number = match name.parse::<usize>() {
+                    Ok(number) => (1..=all_levels.len()).contains(&number).then_some(number),
+                    Err(_) => all_levels
+                        .iter()
+                        .position(|level| level.identifier == *name)
+                        .map(|index| index + 1),
+                };
+                number.unwrap_or_else(|| {
+                    eprintln!("Level {name:?} not found");
+                    std::process::exit(1);
+                })
+            })
+            .collect()
+    };
+
+    let mut report = Report {
+        max_states,
+        levels: Vec::new(),
+    };
+    for number in numbers {
+        let level = all_levels[number - 1];
+        let state = sim::LevelState::from_ldtk(level, &cells).unwrap_or_else(|e| panic!("{e}"));
+        if !state.has_objective() {
+            println!("{number}: {}: no goal or target", level.identifier);
+            report.levels.push(LevelReport {
+                number,
+                identifier: level.identifier.clone(),
+                status: Status::NoObjective,
+                inputs: None,
+                solution: None,
+                explored: 0,
+                seconds: 0.0,
+            });
+            continue;
+        }
+        let start = Instant::now();
+        let result = sim::solve(&state, max_states);
+        let time = start.elapsed();
+        let solution: Option<Vec<String>> = result
+            .solution
+            .map(|solution| solution.iter().map(ToString::to_string).collect());
+        let status = match solution {
+            Some(_) => Status::Solved,
+            None if result.exhausted => Status::Unsolvable,
+            None => Status::Unknown,
+        };
+        match &solution {
+            Some(solution) => {
+                println!(
+                    "{number}: {}: solved in {} inputs ({} states, {time:.2?})",
+                    level.identifier,
+                    solution.len(),
+                    result.explored,
+                );
+                println!("  {}", solution.join(" "));
+            }
+            None if result.exhausted => println!(
+                "{number}: {}: unsolvable ({} states, {time:.2?})",
+                level.identifier, result.explored,
+            ),
+            None => println!(
+                "{number}: {}: no solution within {} states ({time:.2?})",
+                level.identifier, result.explored,
+            ),
+        }
+        report.levels.push(LevelReport {
+            number,
+            identifier: level.identifier.clone(),
+            status,
+            inputs: solution.as_ref().map(Vec::len),
+            solution,
+            explored: result.explored,
+            seconds: time.as_secs_f64(),
+        });
+    }
+
+    if let Some(path) = report_path {
+        let json = serde_json::to_string_pretty(&report).expect("Failed to serialize report");
+        std::fs::write(&path, json).unwrap_or_else(|e| panic!("Failed to write {path:?}: {e}"));
+    }
+
+    let broken: Vec<&LevelReport> = report
+        .levels
+        .iter()
+        .filter(|level| {
+            !matches!(level.status, Status::Solved | Status::NoObjective)
+                || level.inputs.is_some_and(|inputs| inputs < min_inputs)
+        })
+        .collect();
+    if !broken.is_empty() {
+        eprintln!("Broken levels:");
+        for level in broken {
+            eprintln!(
+                "  {}: {} ({:?})",
+                level.number, level.identifier, level.status
+            );
+        }
+        std::process::exit(1);
+    }
+}
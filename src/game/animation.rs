@@ -1,19 +1,17 @@
-use bevy::utils::HashMap;
+use bevy::utils::HashSet;
 
-use super::{player::Movable, *};
+use super::*;
 
 pub struct Plugin;
 
 impl bevy::app::Plugin for Plugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(AnimationEndSfx(None));
-        app.insert_resource(AnimationEndVfx(None));
-
         app.add_system(init_prev_coords);
 
         app.add_system(start_animation.in_schedule(OnEnter(turns::State::Animation)));
         app.add_system(setup_rotation_transform);
         app.add_system(update_transforms.in_set(OnUpdate(turns::State::Animation)));
+        app.add_system(finish_animations.in_set(OnUpdate(turns::State::Animation)));
         app.add_system(stop_animation.in_schedule(OnExit(turns::State::Animation)));
     }
 }
@@ -44,11 +42,37 @@ fn init_prev_coords(
     }
 }
 
-#[derive(Resource)]
-struct AnimationEndSfx(Option<Handle<AudioSource>>);
+/// Time of moving by a cell or rotating by 90 degrees
+const ANIMATION_TIME: f32 = 0.2;
 
-#[derive(Resource)]
-struct AnimationEndVfx(Option<VfxBundle>);
+/// Entity is moving from [PrevCoords] to [GridCoords] during this turn
+#[derive(Component)]
+pub struct Animation {
+    pub duration: f32,
+    /// Shrinks at the previous cell and grows at the new one
+    teleport: bool,
+    end_sfx: Option<Handle<AudioSource>>,
+    end_vfx: Option<VfxBundle>,
+}
+
+impl Animation {
+    /// Returns value from 0 (start of animation) to 1 (end of animation)
+    fn progress(&self, timer: &turns::AnimationTimer) -> f32 {
+        if self.duration <= 0.0 {
+            return 1.0;
+        }
+        (timer.elapsed_secs() / self.duration).min(1.0)
+    }
+
+    fn finish(&mut self, audio: &Audio, commands: &mut Commands) {
+        if let Some(source) = self.end_sfx.take() {
+            audio.play_sfx(source);
+        }
+        if let Some(vfx) = self.end_vfx.take() {
+            commands.spawn(vfx);
+        }
+    }
+}
 
 fn start_animation(
     mut coords: Query<(
@@ -59,8 +83,6 @@ fn start_animation(
     )>,
     mut events: EventReader<turns::MoveEvent>,
     mut commands: Commands,
-    mut end_sfx: ResMut<AnimationEndSfx>,
-    mut end_vfx: ResMut<AnimationEndVfx>,
     audio: Res<Audio>,
     asset_server: Res<AssetServer>,
 ) {
@@ -69,63 +91,94 @@ fn start_animation(
         *prev_coords = PrevCoords(*coords);
         *prev_rot = PrevRotation(*rot);
     }
-    // TODO multiple sfx for each player?
-    let mut sfx = None;
-    // TODO what if multiple players have different animation time?
-    let mut animation_time = 0.2;
-    let mut event_per_player = HashMap::new();
+    let mut turn_time: f32 = 0.0;
+    // Same sound played by multiple entities at once is played once
+    let mut sfx = HashSet::default();
     for event in events.iter() {
-        event_per_player.insert(event.player, event);
-    }
-    for event in event_per_player.into_values() {
-        if let Ok((_, _, mut coords, mut rot)) = coords.get_mut(event.player) {
-            animation_time *= ((rot.0 - event.rotation.0).abs() as f32).max(1.0);
-            *coords = event.coords;
-            *rot = event.rotation;
-            sfx = event.sfx;
-            end_sfx.0 = event.end_sfx.map(|path| asset_server.load(path));
-            if let Some(vfx) = event.vfx.clone() {
-                let entity = commands.spawn(vfx).id();
-                info!("Spawn {entity:?}");
-            }
-            end_vfx.0 = event.end_vfx.clone();
+        let Ok((_, _, mut coords, mut rot)) = coords.get_mut(event.player) else {
+            continue;
+        };
+        let duration = if event.teleport {
+            ANIMATION_TIME * 2.0
+        } else {
+            // Long jumps and punches take longer, like turning more than once
+            let cells = (coords.x - event.coords.x)
+                .abs()
+                .max((coords.y - event.coords.y).abs());
+            let turns = (rot.0 - event.rotation.0).abs();
+            ANIMATION_TIME * cells.max(turns).max(1) as f32
+        };
+        turn_time = turn_time.max(duration);
+        *coords = event.coords;
+        *rot = event.rotation;
+        sfx.extend(event.sfx);
+        if let Some(vfx) = event.vfx.clone() {
+            let entity = commands.spawn(vfx).id();
+            info!("Spawn {entity:?}");
         }
+        commands.entity(event.player).insert(Animation {
+            duration,
+            teleport: event.teleport,
+            end_sfx: event.end_sfx.map(|path| asset_server.load(path)),
+            end_vfx: event.end_vfx.clone(),
+        });
     }
-    if let Some(sfx) = sfx {
-        audio.play_sfx(asset_server.load(sfx));
+    for path in sfx {
+        audio.play_sfx(asset_server.load(path));
     }
-    commands.insert_resource(turns::AnimationTimer::new(animation_time));
+    commands.insert_resource(turns::AnimationTimer::new(turn_time));
 }
 
+/// Where an entity comes from and goes to, animated if it has an [Animation]
+type Animated = (
+    &'static PrevCoords,
+    &'static GridCoords,
+    &'static PrevRotation,
+    &'static Rotation,
+    Option<&'static Animation>,
+    &'static mut Transform,
+);
+
 fn update_transforms(
     timer: Res<turns::AnimationTimer>,
-    mut query: Query<(
-        &PrevCoords,
-        &GridCoords,
-        &PrevRotation,
-        &Rotation,
-        &mut Transform,
-    )>,
+    tile_size: Res<level::TileSize>,
+    mut query: Query<Animated>,
 ) {
-    for (prev_coords, coords, prev_rot, rot, mut transform) in query.iter_mut() {
-        let t = timer.progress();
+    for (prev_coords, coords, prev_rot, rot, animation, mut transform) in query.iter_mut() {
+        let t = animation.map_or(1.0, |animation| animation.progress(&timer));
 
         let prev_coords = &prev_coords.0;
-        let tile_size = IVec2::new(16, 16); // TODO load from ldtk
-        let prev_pos = grid_coords_to_translation(*prev_coords, tile_size);
-        let next_pos = grid_coords_to_translation(*coords, tile_size);
+        let prev_pos = grid_coords_to_translation(*prev_coords, **tile_size);
+        let next_pos = grid_coords_to_translation(*coords, **tile_size);
         let prev_rot = &prev_rot.0;
         let prev_rot = prev_rot.to_radians();
         let rot = rot.to_radians();
         let delta_pos = next_pos - prev_pos;
         let delta_rot = rot - prev_rot;
 
-        if delta_rot != 0.0 {
+        if animation.is_some_and(|animation| animation.teleport) {
+            let (pos, rot) = if t < 0.5 {
+                (prev_pos, prev_rot)
+            } else {
+                (next_pos, rot)
+            };
+            *transform = Transform::from_translation(pos.extend(transform.translation.z))
+                .with_rotation(Quat::from_rotation_z(rot))
+                .with_scale(Vec3::splat((1.0 - 2.0 * t).abs()));
+            continue;
+        }
+        transform.scale = Vec3::ONE;
+
+        if delta_rot != 0.0 && delta_pos == Vec2::ZERO {
+            // Turning in place, around the cell center
+            transform.translation = prev_pos.extend(transform.translation.z);
+            transform.rotation = Quat::from_rotation_z(prev_rot + delta_rot * t);
+        } else if delta_rot != 0.0 {
             let rotation_origin = prev_pos
                 + delta_pos / 2.0
                 + Vec2::new(0.0, 1.0).rotate(delta_pos) / (delta_rot / 2.0).tan() / 2.0;
 
-            let border_radius: f32 = delta_rot.abs() / PI * 8.0;
+            let border_radius: f32 = delta_rot.abs() / PI * tile_size.x as f32 / 2.0;
 
             let extra_len =
                 (1.0 / ((1.0 - (t - 0.5).abs() * 2.0) * PI / 4.0).cos() - 1.0) * border_radius;
@@ -147,17 +200,29 @@ fn update_transforms(
     }
 }
 
-/// When animation stops, check if we need to spawn some sound/visual effects
-fn stop_animation(
-    mut end_sfx: ResMut<AnimationEndSfx>,
-    mut end_vfx: ResMut<AnimationEndVfx>,
+/// Spawns sound/visual effects of entities that stopped moving
+fn finish_animations(
+    timer: Res<turns::AnimationTimer>,
+    mut query: Query<(Entity, &mut Animation)>,
     audio: Res<Audio>,
     mut commands: Commands,
 ) {
-    if let Some(source) = end_sfx.0.take() {
-        audio.play_sfx(source);
+    for (entity, mut animation) in query.iter_mut() {
+        if animation.progress(&timer) >= 1.0 {
+            animation.finish(&audio, &mut commands);
+            commands.entity(entity).remove::<Animation>();
+        }
     }
-    if let Some(vfx) = end_vfx.0.take() {
-        commands.spawn(vfx);
+}
+
+/// When the turn's animation stops, finish the ones that did not get to it this frame
+fn stop_animation(
+    mut query: Query<(Entity, &mut Animation)>,
+    audio: Res<Audio>,
+    mut commands: Commands,
+) {
+    for (entity, mut animation) in query.iter_mut() {
+        animation.finish(&audio, &mut commands);
+        commands.entity(entity).remove::<Animation>();
     }
 }
@@ -0,0 +1,26 @@
+use super::*;
+
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<ConveyorBundle>("Conveyor");
+    }
+}
+
+/// Carries resting entities towards its `Side`, see [sim::EntityKind::Conveyor]
+#[derive(Default, Component)]
+pub struct Conveyor;
+
+#[derive(Bundle, LdtkEntity)]
+struct ConveyorBundle {
+    conveyor: Conveyor,
+    #[grid_coords]
+    position: GridCoords,
+    #[from_entity_instance]
+    rotation: Rotation,
+    #[sprite_sheet_bundle]
+    sprite_sheet: SpriteSheetBundle,
+    #[with(entity_name)]
+    name: Name,
+}
@@ -0,0 +1,113 @@
+//! Loads `world.powers.json` and `world.cells.json` with the asset server, see [sim::PowerDef] and [sim::CellDef]
+
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+
+use super::*;
+
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<PowerDefs>();
+        app.add_asset::<CellDefs>();
+        app.add_asset_loader(DefsLoader);
+        app.add_startup_system(setup);
+        app.add_system(init.run_if(not(resource_exists::<Loaded>())));
+        app.add_system(warn_modified);
+    }
+}
+
+#[derive(Debug, TypeUuid)]
+#[uuid = "4b0c5b1e-6f35-4a0e-9a51-3f2d6f0b8c11"]
+pub struct PowerDefs(pub Vec<sim::PowerDef>);
+
+#[derive(Debug, TypeUuid)]
+#[uuid = "9d7e2c44-1a6b-4f7e-b0d3-5c8e1f2a7b90"]
+pub struct CellDefs(pub Vec<sim::CellDef>);
+
+/// Present once the definitions are set, levels are only spawned after that
+#[derive(Resource)]
+pub struct Loaded;
+
+#[derive(Resource)]
+struct Handles {
+    powers: Handle<PowerDefs>,
+    cells: Handle<CellDefs>,
+}
+
+/// Tells the definition files apart by their extension, so that other json files are left alone
+struct DefsLoader;
+
+impl AssetLoader for DefsLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let path = load_context.path().to_owned();
+            let name = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or_default();
+            if name.ends_with(".powers.json") {
+                load_context
+                    .set_default_asset(LoadedAsset::new(PowerDefs(serde_json::from_slice(bytes)?)));
+            } else if name.ends_with(".cells.json") {
+                load_context
+                    .set_default_asset(LoadedAsset::new(CellDefs(serde_json::from_slice(bytes)?)));
+            } else {
+                return Err(bevy::asset::Error::msg(format!(
+                    "{path:?} is not a definition file"
+                )));
+            }
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["powers.json", "cells.json"]
+    }
+}
+
+fn setup(asset_server: Res<AssetServer>, mut commands: Commands) {
+    commands.insert_resource(Handles {
+        powers: asset_server.load("world.powers.json"),
+        cells: asset_server.load("world.cells.json"),
+    });
+}
+
+fn init(
+    handles: Res<Handles>,
+    powers: Res<Assets<PowerDefs>>,
+    cells: Res<Assets<CellDefs>>,
+    mut commands: Commands,
+) {
+    let (Some(powers), Some(cells)) = (powers.get(&handles.powers), cells.get(&handles.cells))
+    else {
+        return;
+    };
+    sim::PowerDef::init(powers.0.clone());
+    sim::CellDef::init(cells.0.clone());
+    commands.insert_resource(Loaded);
+}
+
+/// Powers and cells are already in use, so changes only apply after a restart
+fn warn_modified(
+    mut powers: EventReader<AssetEvent<PowerDefs>>,
+    mut cells: EventReader<AssetEvent<CellDefs>>,
+) {
+    let powers_modified = powers
+        .iter()
+        .any(|event| matches!(event, AssetEvent::Modified { .. }));
+    let cells_modified = cells
+        .iter()
+        .any(|event| matches!(event, AssetEvent::Modified { .. }));
+    if powers_modified || cells_modified {
+        warn!("Definitions changed, restart the game to use them");
+    }
+}
@@ -0,0 +1,89 @@
+//! Death animation and the prompt shown after failing a level
+
+use super::*;
+
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(start_dying);
+        app.add_system(show_prompt.in_schedule(OnEnter(turns::State::Failed)));
+        app.add_system(update_dying.in_set(OnUpdate(turns::State::Failed)));
+        app.add_system(stop_dying.in_schedule(OnExit(turns::State::Failed)));
+    }
+}
+
+/// Time of spinning away after touching a hazard
+const DEATH_TIME: f32 = 0.6;
+
+/// Player that failed the level, spins and shrinks until the level is restarted or undone
+#[derive(Component)]
+struct Dying {
+    elapsed: f32,
+}
+
+#[derive(Component)]
+struct FailPrompt;
+
+fn start_dying(
+    mut failures: EventReader<turns::LevelFailed>,
+    mut commands: Commands,
+    audio: Res<Audio>,
+    asset_server: Res<AssetServer>,
+) {
+    for failure in failures.iter() {
+        commands
+            .entity(failure.player)
+            .insert(Dying { elapsed: 0.0 });
+        audio.play_sfx(asset_server.load("sfx/hitHurt.wav"));
+    }
+}
+
+fn update_dying(time: Res<Time>, mut query: Query<(&mut Dying, &mut Transform)>) {
+    for (mut dying, mut transform) in query.iter_mut() {
+        let t = (dying.elapsed / DEATH_TIME).min(1.0);
+        dying.elapsed += time.delta_seconds();
+        let t_next = (dying.elapsed / DEATH_TIME).min(1.0);
+        transform.rotate_z((t_next - t) * 2.0 * PI);
+        transform.scale = Vec3::splat(1.0 - t_next);
+    }
+}
+
+fn show_prompt(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        TextBundle::from_section(
+            "Z to undo, R to restart",
+            TextStyle {
+                font: asset_server.load("Pixellari.ttf"),
+                font_size: 60.0,
+                color: Color::WHITE,
+            },
+        )
+        .with_text_alignment(TextAlignment::Center)
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect {
+                top: Val::Px(5.0),
+                left: Val::Px(15.0),
+                ..default()
+            },
+            ..default()
+        }),
+        FailPrompt,
+    ));
+}
+
+/// Undo puts the player back in place, only the scale is left to reset
+fn stop_dying(
+    mut dying: Query<(Entity, &mut Transform), With<Dying>>,
+    prompts: Query<Entity, With<FailPrompt>>,
+    mut commands: Commands,
+) {
+    for (entity, mut transform) in dying.iter_mut() {
+        transform.scale = Vec3::ONE;
+        commands.entity(entity).remove::<Dying>();
+    }
+    for prompt in prompts.iter() {
+        commands.entity(prompt).despawn();
+    }
+}
@@ -5,15 +5,28 @@ pub struct Plugin;
 impl bevy::app::Plugin for Plugin {
     fn build(&self, app: &mut App) {
         app.register_ldtk_entity::<GoalBundle>("Goal");
-        app.add_turn_system(finish_level, turns::TurnOrder::ApplySideEffects);
+        app.register_ldtk_entity::<TargetBundle>("Target");
+        app.add_system(next_level);
     }
 }
 
+/// Reached by the players it accepts, see [sim::GoalRequirement]
+#[derive(Component, Clone, Copy)]
+pub struct Goal(pub sim::GoalRequirement);
+
+impl From<&EntityInstance> for Goal {
+    fn from(entity: &EntityInstance) -> Self {
+        Self(entity.into())
+    }
+}
+
+/// Has to be covered by a box, see [sim::EntityKind::Target]
 #[derive(Default, Component)]
-struct Goal;
+pub struct Target;
 
 #[derive(Bundle, LdtkEntity)]
 struct GoalBundle {
+    #[from_entity_instance]
     goal: Goal,
     #[grid_coords]
     position: GridCoords,
@@ -23,25 +36,29 @@ struct GoalBundle {
     name: Name,
 }
 
-fn finish_level(
+#[derive(Bundle, LdtkEntity)]
+struct TargetBundle {
+    target: Target,
+    #[grid_coords]
+    position: GridCoords,
+    #[sprite_sheet_bundle]
+    sprite_sheet: SpriteSheetBundle,
+    #[with(entity_name)]
+    name: Name,
+}
+
+/// Goes to the next level, once per [turns::LevelCompleted]
+fn next_level(
+    mut completions: EventReader<turns::LevelCompleted>,
     mut level_selection: ResMut<LevelSelection>,
-    players: Query<(&GridCoords, &Rotation), With<Player>>,
-    goals: Query<&GridCoords, With<Goal>>,
     audio: Res<Audio>,
     asset_server: Res<AssetServer>,
 ) {
-    for (player_coords, player_rotation) in players.iter() {
-        if player_rotation.0 % 4 != 0 {
-            continue;
-        }
-        for goal_coords in goals.iter() {
-            if player_coords == goal_coords {
-                audio.play_sfx(asset_server.load("sfx/finishLevel.wav"));
-                match *level_selection {
-                    LevelSelection::Index(ref mut index) => *index += 1,
-                    _ => unreachable!(),
-                }
-            }
+    for _ in completions.iter() {
+        audio.play_sfx(asset_server.load("sfx/finishLevel.wav"));
+        match *level_selection {
+            LevelSelection::Index(ref mut index) => *index += 1,
+            _ => unreachable!(),
         }
     }
 }
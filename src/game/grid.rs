@@ -0,0 +1,132 @@
+//! Index of what is in every cell, kept in sync with the world instead of collected every step
+
+use std::sync::Arc;
+
+use bevy::utils::HashMap;
+
+use super::{
+    goal::{Goal, Target},
+    level::{Blocking, LevelCell},
+    player::Pushable,
+    side::{Powerup, Trigger},
+    *,
+};
+
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Grid>();
+        // Before the turn systems, so that they see the cells of a new level
+        app.add_system(update_grid.in_base_set(CoreSet::PreUpdate));
+        if cfg!(debug_assertions) {
+            app.add_system(check_grid.in_schedule(OnEnter(turns::State::WaitingForInput)));
+        }
+    }
+}
+
+/// Which entities occupy which cells, kept in sync with [GridCoords] and [OccupancyQuery]
+#[derive(Resource, Default)]
+pub struct Grid {
+    pub occupants: sim::Grid<Entity>,
+    /// What the [LevelCell]s do, shared by every [gathered](sync::SimWorld::gather) state
+    pub cells: Arc<HashMap<GridCoords, sim::Cell>>,
+    cell_entities: HashMap<Entity, GridCoords>,
+}
+
+impl Grid {
+    /// Adds, moves or removes the entity, depending on what it does now
+    fn update(&mut self, entity: Entity, coords: GridCoords, occupancy: sim::Occupancy) {
+        // Things like background tiles don't matter
+        if occupancy.is_empty() {
+            self.occupants.remove(entity);
+        } else {
+            self.occupants.insert(entity, coords, occupancy);
+        }
+    }
+}
+
+#[derive(WorldQuery)]
+pub struct OccupancyQuery {
+    blocking: Option<&'static Blocking>,
+    trigger: Option<&'static Trigger>,
+    pushable: Option<&'static Pushable>,
+    powerup: Option<&'static Powerup>,
+    goal: Option<&'static Goal>,
+    target: Option<&'static Target>,
+}
+
+impl OccupancyQueryItem<'_> {
+    pub fn occupancy(&self) -> sim::Occupancy {
+        sim::Occupancy {
+            blocking: self.blocking.is_some(),
+            trigger: self.trigger.is_some(),
+            pushable: self.pushable.is_some(),
+            powerup: self.powerup.is_some(),
+            goal: self.goal.is_some() || self.target.is_some(),
+        }
+    }
+}
+
+/// Level cells get [Blocking] and [Trigger] after they spawn, doors lose [Blocking] while open
+type OccupancyChanged = Or<(Changed<GridCoords>, Added<Blocking>, Added<Trigger>)>;
+
+fn update_grid(
+    mut grid: ResMut<Grid>,
+    changed: Query<(Entity, &GridCoords, OccupancyQuery), OccupancyChanged>,
+    occupants: Query<(&GridCoords, OccupancyQuery)>,
+    new_cells: Query<(Entity, &GridCoords, &LevelCell), Added<LevelCell>>,
+    mut removed: RemovedComponents<GridCoords>,
+    mut unblocked: RemovedComponents<Blocking>,
+) {
+    let mut removed_cells = Vec::new();
+    for entity in removed.iter() {
+        grid.occupants.remove(entity);
+        if let Some(coords) = grid.cell_entities.remove(&entity) {
+            removed_cells.push(coords);
+        }
+    }
+    for entity in unblocked.iter() {
+        if let Ok((coords, occupancy)) = occupants.get(entity) {
+            grid.update(entity, *coords, occupancy.occupancy());
+        }
+    }
+    for (entity, coords, occupancy) in changed.iter() {
+        grid.update(entity, *coords, occupancy.occupancy());
+    }
+
+    if removed_cells.is_empty() && new_cells.is_empty() {
+        return;
+    }
+    let grid = &mut *grid;
+    let cells = Arc::make_mut(&mut grid.cells);
+    for coords in removed_cells {
+        cells.remove(&coords);
+    }
+    for (entity, coords, cell) in new_cells.iter() {
+        cells.insert(*coords, cell.0);
+        grid.cell_entities.insert(entity, *coords);
+    }
+}
+
+/// Checks that the grid is consistent with the world
+pub fn check_grid(
+    grid: Res<Grid>,
+    query: Query<(Entity, &GridCoords, OccupancyQuery)>,
+    cells: Query<(&GridCoords, &LevelCell)>,
+) {
+    let expected = query
+        .iter()
+        .map(|(entity, coords, occupancy)| (entity, *coords, occupancy.occupancy()))
+        .filter(|(.., occupancy)| !occupancy.is_empty());
+    if let Err(error) = grid.occupants.check(expected) {
+        error!("Grid is out of sync: {error}");
+    }
+    let expected: HashMap<GridCoords, sim::Cell> = cells
+        .iter()
+        .map(|(coords, cell)| (*coords, cell.0))
+        .collect();
+    if *grid.cells != expected {
+        error!("Grid cells are out of sync");
+    }
+}
@@ -0,0 +1,166 @@
+//! Undo & redo of turns
+
+use super::{
+    animation::{PrevCoords, PrevRotation},
+    player::PlayerActions,
+    replay::Turn,
+    *,
+};
+
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<History>();
+        app.add_system(clear_history);
+        app.add_system(record_turn.in_schedule(OnEnter(turns::State::WaitingForInput)));
+        app.add_systems(
+            (undo_redo, apply_system_buffers)
+                .chain()
+                .in_set(OnUpdate(turns::State::WaitingForInput))
+                .in_set(UndoRedo)
+                .after(player::keyboard_input)
+                .before(player::update_player_input),
+        );
+        app.add_system(
+            undo_failure
+                .in_set(OnUpdate(turns::State::Failed))
+                .in_set(UndoRedo)
+                .after(player::keyboard_input),
+        );
+    }
+}
+
+/// Restores a snapshot from the undo and redo in [PlayerActions], fill them in before
+#[derive(SystemSet, Hash, PartialEq, Eq, Debug, Copy, Clone)]
+pub struct UndoRedo;
+
+struct Snapshot {
+    state: sim::LevelState,
+    entities: Vec<Entity>,
+    /// [PrevCoords] and [PrevRotation] for every entity
+    prev: Vec<(GridCoords, Rotation)>,
+    /// Moves made to get here, restored with the state so that replays stay in sync
+    turn: u32,
+}
+
+/// Every state the current level was waiting for input in
+#[derive(Resource, Default)]
+struct History {
+    snapshots: Vec<Snapshot>,
+    /// Index of the snapshot we are at, the ones after it can be redone
+    current: usize,
+}
+
+fn clear_history(mut history: ResMut<History>, levels: Query<(), Added<Handle<LdtkLevel>>>) {
+    if !levels.is_empty() {
+        *history = History::default();
+    }
+}
+
+fn record_turn(
+    mut history: ResMut<History>,
+    sim: sync::SimWorld,
+    prev: Query<(&PrevCoords, Option<&PrevRotation>)>,
+    turn: Res<Turn>,
+) {
+    let (state, entities) = sim.gather();
+    if history
+        .snapshots
+        .get(history.current)
+        .is_some_and(|snapshot| snapshot.state == state)
+    {
+        // Nothing happened, like trying to move into a wall
+        return;
+    }
+    let prev = state
+        .iter()
+        .map(|(id, entity)| match prev.get(entities[id.0]) {
+            Ok((coords, rotation)) => (coords.0, rotation.map_or(entity.rotation, |rot| rot.0)),
+            Err(_) => (entity.coords, entity.rotation),
+        })
+        .collect();
+    if !history.snapshots.is_empty() {
+        history.current += 1;
+    }
+    let current = history.current;
+    history.snapshots.truncate(current);
+    history.snapshots.push(Snapshot {
+        state,
+        entities,
+        prev,
+        turn: turn.0,
+    });
+}
+
+fn undo_redo(
+    actions: Res<PlayerActions>,
+    mut history: ResMut<History>,
+    mut sim: sync::SimWorld,
+    mut query: Query<(&mut Transform, &mut PrevCoords, Option<&mut PrevRotation>)>,
+    tile_size: Res<level::TileSize>,
+    mut turn: ResMut<Turn>,
+) {
+    let current = if actions.undo {
+        let Some(current) = history.current.checked_sub(1) else {
+            return;
+        };
+        current
+    } else if actions.redo {
+        if history.current + 1 >= history.snapshots.len() {
+            return;
+        }
+        history.current + 1
+    } else {
+        return;
+    };
+    history.current = current;
+    restore(&history, &mut sim, &mut query, *tile_size, &mut turn);
+}
+
+/// Undoing after failing the level goes back to before the move that failed it
+fn undo_failure(
+    actions: Res<PlayerActions>,
+    history: Res<History>,
+    mut sim: sync::SimWorld,
+    mut query: Query<(&mut Transform, &mut PrevCoords, Option<&mut PrevRotation>)>,
+    tile_size: Res<level::TileSize>,
+    mut turn: ResMut<Turn>,
+    mut next_state: ResMut<NextState<turns::State>>,
+) {
+    if !actions.undo || history.snapshots.is_empty() {
+        return;
+    }
+    restore(&history, &mut sim, &mut query, *tile_size, &mut turn);
+    next_state.set(turns::State::WaitingForInput);
+}
+
+/// Brings the world back to the current snapshot
+fn restore(
+    history: &History,
+    sim: &mut sync::SimWorld,
+    query: &mut Query<(&mut Transform, &mut PrevCoords, Option<&mut PrevRotation>)>,
+    tile_size: level::TileSize,
+    turn: &mut Turn,
+) {
+    let current = history.current;
+    let snapshot = &history.snapshots[current];
+    info!("Restoring turn {current}");
+    sim.restore(&snapshot.state, &snapshot.entities);
+    turn.0 = snapshot.turn;
+    for ((id, entity), &(prev_coords, prev_rotation)) in snapshot.state.iter().zip(&snapshot.prev) {
+        let Ok((mut transform, mut prev_coords_component, prev_rotation_component)) =
+            query.get_mut(snapshot.entities[id.0])
+        else {
+            continue;
+        };
+        // Same as the end of the animation that led here
+        let pos = grid_coords_to_translation(entity.coords, *tile_size);
+        transform.translation = pos.extend(transform.translation.z);
+        transform.rotation = Quat::from_rotation_z(entity.rotation.to_radians());
+        prev_coords_component.0 = prev_coords;
+        if let Some(mut prev_rotation_component) = prev_rotation_component {
+            prev_rotation_component.0 = prev_rotation;
+        }
+    }
+}
@@ -4,7 +4,10 @@ pub struct Plugin;
 
 impl bevy::app::Plugin for Plugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(setup);
+        app.add_system(setup.run_if(resource_added::<defs::Loaded>()));
+
+        app.init_resource::<TileSize>();
+        app.add_system(update_tile_size);
 
         app.insert_resource(LevelSelection::Index(0));
         app.insert_resource(LdtkSettings {
@@ -16,12 +19,11 @@ impl bevy::app::Plugin for Plugin {
         app.add_system(level_restart);
         app.add_system(change_level_cheats);
 
-        app.register_ldtk_int_cell::<BlockBundle>(1);
-        app.register_ldtk_int_cell::<DisableBundle>(6);
-        app.register_ldtk_int_cell::<CloudBundle>(7);
+        app.add_system(setup_cells);
     }
 }
 
+/// Spawns the world once the definitions its entities and cells refer to are loaded
 fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
     commands.spawn((
         LdtkWorldBundle {
@@ -33,6 +35,44 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
     ));
 }
 
+/// Size of a grid cell in pixels, taken from the layer definitions of the project
+#[derive(Resource, Deref, Clone, Copy)]
+pub struct TileSize(pub IVec2);
+
+impl Default for TileSize {
+    /// Default grid size of LDtk, used until the project is loaded
+    fn default() -> Self {
+        Self(IVec2::splat(16))
+    }
+}
+
+fn update_tile_size(
+    mut events: EventReader<AssetEvent<LdtkAsset>>,
+    assets: Res<Assets<LdtkAsset>>,
+    mut tile_size: ResMut<TileSize>,
+) {
+    for event in events.iter() {
+        let (AssetEvent::Created { handle } | AssetEvent::Modified { handle }) = event else {
+            continue;
+        };
+        let Some(asset) = assets.get(handle) else {
+            continue;
+        };
+        let mut sizes = asset
+            .project
+            .defs
+            .layers
+            .iter()
+            .map(|layer| layer.grid_size);
+        let Some(size) = sizes.next() else { continue };
+        if sizes.any(|other| other != size) {
+            warn!("Layers have different grid sizes, using {size}px");
+        }
+        info!("Tile size is {size}px");
+        *tile_size = TileSize(IVec2::splat(size));
+    }
+}
+
 #[derive(Component)]
 struct LevelLabel;
 
@@ -45,8 +85,11 @@ fn level_label(
     asset_server: Res<AssetServer>,
     mut commands: Commands,
 ) {
-    let main = main.get_single().unwrap();
-    let main = main_assets.get(main).unwrap();
+    // The world is only spawned once the definitions are loaded, and loads after that
+    let Ok(main) = main.get_single() else { return };
+    let Some(main) = main_assets.get(main) else {
+        return;
+    };
     for (_level_entity, level) in &levels {
         for label in &labels {
             commands.entity(label).despawn();
@@ -84,20 +127,41 @@ fn level_label(
 #[derive(Default, Component)]
 pub struct Blocking;
 
-#[derive(Bundle, LdtkIntCell)]
-struct BlockBundle {
-    blocking: Blocking,
-    trigger: side::Trigger,
-}
-
-#[derive(Bundle, LdtkIntCell)]
-struct DisableBundle {
-    blocking: Blocking,
-}
+/// What an IntGrid cell does, see [sim::CellTable]
+#[derive(Debug, Component, Clone, Copy, Deref)]
+pub struct LevelCell(pub sim::Cell);
 
-#[derive(Bundle, LdtkIntCell)]
-struct CloudBundle {
-    trigger: side::Trigger,
+/// Looks up new IntGrid cells in the [sim::CellTable] of the project
+///
+/// Panics on values the table does not know, instead of leaving cells that silently do nothing.
+fn setup_cells(
+    cells: Query<(Entity, &IntGridCell), Added<IntGridCell>>,
+    projects: Query<&Handle<LdtkAsset>>,
+    assets: Res<Assets<LdtkAsset>>,
+    mut commands: Commands,
+) {
+    if cells.is_empty() {
+        return;
+    }
+    let Some(project) = projects
+        .get_single()
+        .ok()
+        .and_then(|handle| assets.get(handle))
+    else {
+        return;
+    };
+    let table = sim::CellTable::from_ldtk(&project.project);
+    for (entity, cell) in cells.iter() {
+        let cell = table.get(cell.value).unwrap_or_else(|e| panic!("{e}"));
+        let mut commands = commands.entity(entity);
+        commands.insert(LevelCell(cell));
+        if cell.blocking {
+            commands.insert(Blocking);
+        }
+        if cell.trigger {
+            commands.insert(side::Trigger);
+        }
+    }
 }
 
 fn level_restart(
@@ -1,19 +1,24 @@
 use std::f32::consts::PI;
 
-use bevy::{
-    ecs::query::{ReadOnlyWorldQuery, WorldQuery},
-    math::Vec3Swizzles,
-    prelude::*,
-};
+use bevy::{ecs::query::WorldQuery, math::Vec3Swizzles, prelude::*};
 use bevy_ecs_ldtk::{prelude::*, utils::grid_coords_to_translation};
 
 mod animation;
 mod audio;
 mod background;
+mod conveyor;
+mod defs;
+mod failure;
 mod goal;
+mod grid;
+mod history;
 mod level;
 mod player;
+pub mod replay;
 mod side;
+mod signals;
+pub mod sim;
+mod sync;
 mod turns;
 mod util;
 mod vfx;
@@ -24,18 +29,13 @@ use player::Player;
 use turns::AppExt as _;
 use util::{Direction, *}; // Need to shadow Direction from bevy prelude
 
+/// The whole game
 pub struct Plugin;
 
 impl bevy::app::Plugin for Plugin {
     fn build(&self, app: &mut App) {
-        app.add_plugin(turns::Plugin);
-        app.add_plugin(side::Plugin);
-        app.add_plugin(goal::Plugin);
-        app.add_plugin(vfx::Plugin);
-        app.add_plugin(animation::Plugin);
-        app.add_plugin(player::Plugin);
+        app.add_plugin(HeadlessPlugin);
         app.add_plugin(audio::Plugin);
-        app.add_plugin(level::Plugin);
         app.add_plugin(background::Plugin);
 
         app.add_startup_system(setup);
@@ -47,6 +47,29 @@ impl bevy::app::Plugin for Plugin {
     }
 }
 
+/// Everything except the camera, background and music, so it can run without a window
+pub struct HeadlessPlugin;
+
+impl bevy::app::Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(defs::Plugin);
+        app.add_plugin(turns::Plugin);
+        app.add_plugin(sync::Plugin);
+        app.add_plugin(grid::Plugin);
+        app.add_plugin(history::Plugin);
+        app.add_plugin(side::Plugin);
+        app.add_plugin(goal::Plugin);
+        app.add_plugin(signals::Plugin);
+        app.add_plugin(conveyor::Plugin);
+        app.add_plugin(failure::Plugin);
+        app.add_plugin(vfx::Plugin);
+        app.add_plugin(animation::Plugin);
+        app.add_plugin(player::Plugin);
+        app.add_plugin(replay::Plugin);
+        app.add_plugin(level::Plugin);
+    }
+}
+
 #[derive(Component, Ord, PartialOrd, PartialEq, Eq)]
 struct PlayerIndex(i32);
 
@@ -1,16 +1,28 @@
-use super::{level::Blocking, side::PickupSideEffects, *};
+use super::{side::PickupSideEffects, *};
 
 pub struct Plugin;
 
 impl bevy::app::Plugin for Plugin {
     fn build(&self, app: &mut App) {
-        app.add_system(update_player_input.in_set(OnUpdate(turns::State::WaitingForInput)));
+        app.init_resource::<PlayerActions>();
+        app.add_system(keyboard_input.run_if(accepts_input));
         app.add_system(
-            move_system
+            update_player_input
+                .in_set(OnUpdate(turns::State::WaitingForInput))
+                .after(keyboard_input),
+        );
+        app.add_system(
+            switch_player
                 .in_set(OnUpdate(turns::State::WaitingForInput))
                 .after(update_player_input),
         );
-        app.add_turn_system(falling_system, turns::TurnOrder::ApplySideEffects);
+        // The player switched to moves on the same frame, see [sim::play_frame]
+        app.add_systems(
+            (apply_system_buffers, move_system)
+                .chain()
+                .in_set(OnUpdate(turns::State::WaitingForInput))
+                .after(switch_player),
+        );
 
         app.register_ldtk_entity::<PlayerBundle>("Player");
         app.register_ldtk_entity::<CrateBundle>("Crate");
@@ -30,6 +42,8 @@ struct BoxBundle {
     #[from_entity_instance]
     rotation: Rotation,
     #[from_entity_instance]
+    push_motion: PushMotion,
+    #[from_entity_instance]
     entity_instance: EntityInstance,
     player_input: player::Input, // TODO remove
     pickup: PickupSideEffects,
@@ -49,6 +63,8 @@ struct DisableBoxBundle {
     #[from_entity_instance]
     rotation: Rotation,
     #[from_entity_instance]
+    push_motion: PushMotion,
+    #[from_entity_instance]
     entity_instance: EntityInstance,
     player_input: player::Input, // TODO remove
     pickup: PickupSideEffects,
@@ -114,6 +130,13 @@ pub struct SelectedPlayer;
 #[derive(Component)]
 pub struct SlideMove;
 
+/// Present while [sim::EntityState::fall_distance] or [sim::EntityState::bouncing] is set
+#[derive(Component)]
+pub struct Fall {
+    pub distance: i32,
+    pub bouncing: bool,
+}
+
 #[derive(Component)]
 pub struct OverrideGravity(pub Vec<IVec2>);
 
@@ -123,13 +146,34 @@ pub struct Input {
     pub direction: Direction,
 }
 
-fn update_player_input(
+/// What the player wants to do, filled from the keyboard or from a [replay](super::replay)
+#[derive(Resource, Default)]
+pub struct PlayerActions {
+    pub direction: Direction,
+    /// Offset in the list of players to switch to, 0 means no switch
+    pub switch_player: i32,
+    /// Go back to the previous state, see [history](super::history)
+    pub undo: bool,
+    /// Go forward to the state that was undone
+    pub redo: bool,
+}
+
+/// Input is read while waiting for a move, and after failing the level to undo it
+pub fn accepts_input(state: Res<State<turns::State>>) -> bool {
+    matches!(
+        state.0,
+        turns::State::WaitingForInput | turns::State::Failed
+    )
+}
+
+/// The first player is only selected on the first frame waiting for input, see [switch_player]
+pub fn player_selected(selected: Query<(), With<SelectedPlayer>>) -> bool {
+    !selected.is_empty()
+}
+
+pub fn keyboard_input(
     keyboard_input: Res<bevy::input::Input<KeyCode>>,
-    players: Query<(Entity, &PlayerIndex, &GridCoords, Option<&SelectedPlayer>), With<Player>>,
-    mut inputs: Query<&mut Input, With<SelectedPlayer>>,
-    mut commands: Commands,
-    audio: Res<Audio>,
-    asset_server: Res<AssetServer>,
+    mut actions: ResMut<PlayerActions>,
 ) {
     let mut dir = 0;
     if keyboard_input.any_pressed([KeyCode::A, KeyCode::Left]) {
@@ -138,208 +182,90 @@ fn update_player_input(
     if keyboard_input.any_pressed([KeyCode::D, KeyCode::Right]) {
         dir += 1;
     }
-    for mut input in inputs.iter_mut() {
-        input.direction = match dir.cmp(&0) {
-            std::cmp::Ordering::Less => Direction::Left,
-            std::cmp::Ordering::Equal => Direction::None,
-            std::cmp::Ordering::Greater => Direction::Right,
-        };
+    actions.direction = match dir.cmp(&0) {
+        std::cmp::Ordering::Less => Direction::Left,
+        std::cmp::Ordering::Equal => Direction::None,
+        std::cmp::Ordering::Greater => Direction::Right,
+    };
+
+    actions.switch_player = 0;
+    if keyboard_input.any_just_pressed([KeyCode::Tab, KeyCode::W, KeyCode::Up]) {
+        actions.switch_player = 1;
+    }
+    if keyboard_input.any_just_pressed([KeyCode::S, KeyCode::Down]) {
+        actions.switch_player = -1;
     }
 
-    if !players.is_empty() {
-        let mut dir = 0;
-        if keyboard_input.any_just_pressed([KeyCode::Tab, KeyCode::W, KeyCode::Up]) {
-            dir = 1;
-        }
-        if keyboard_input.any_just_pressed([KeyCode::S, KeyCode::Down]) {
-            dir = -1;
-        }
+    actions.undo = keyboard_input.any_just_pressed([KeyCode::Z, KeyCode::U]);
+    actions.redo = keyboard_input.just_pressed(KeyCode::Y);
+    if actions.undo || actions.redo {
+        // Undo takes the whole frame, a held direction moves on the next one
+        actions.direction = Direction::None;
+        actions.switch_player = 0;
+    }
+}
 
-        if dir != 0 || inputs.is_empty() {
-            let mut players: Vec<(&PlayerIndex, (i32, i32), Entity, bool)> = players
-                .iter()
-                .map(|(entity, index, coords, selected)| {
-                    (index, (coords.x, coords.y), entity, selected.is_some())
-                })
-                .collect();
-            players.sort();
-            let selected = players.iter().position(|&(.., selected)| selected);
-            if let Some(selected) = selected {
-                commands
-                    .entity(players[selected].2)
-                    .remove::<SelectedPlayer>();
-            }
-            let to_select = (selected.unwrap_or(0) as isize + players.len() as isize + dir)
-                % players.len() as isize;
-            let new_selected_player = players[to_select as usize].2;
-            commands.entity(new_selected_player).insert(SelectedPlayer);
-            audio.play_sfx(asset_server.load("sfx/selectPlayer.wav"));
-            commands.spawn(VfxBundle::new(
-                {
-                    let (x, y) = players[to_select as usize].1;
-                    GridCoords { x, y }
-                },
-                0,
-                "animation/PLAYER_CHANGE.png",
-                None,
-                true,
-                false,
-            ));
-        }
+pub fn update_player_input(
+    actions: Res<PlayerActions>,
+    mut inputs: Query<&mut Input, With<SelectedPlayer>>,
+) {
+    for mut input in inputs.iter_mut() {
+        input.direction = actions.direction;
     }
 }
-#[allow(clippy::type_complexity)]
-pub fn move_system(
-    mut next_state: ResMut<NextState<turns::State>>,
-    pushable: Query<(Entity, &GridCoords, &Rotation), With<Pushable>>,
-    blocked: Query<BlockedQuery, With<Blocking>>,
-    players: Query<
-        (
-            Entity,
-            &Input,
-            &GridCoords,
-            &Rotation,
-            Option<&OverrideGravity>,
-            Option<&SlideMove>,
-        ),
-        With<SelectedPlayer>,
-    >,
-    mut events: EventWriter<turns::MoveEvent>,
+
+/// Changes the player like [sim::Input::SwitchPlayer], the first one is selected if none is
+fn switch_player(
+    actions: Res<PlayerActions>,
+    players: Query<(), With<Player>>,
+    selected: Query<(), With<SelectedPlayer>>,
+    mut sim: sync::SimWorld,
+    mut commands: Commands,
+    audio: Res<Audio>,
+    asset_server: Res<AssetServer>,
 ) {
-    for (player, input, coords, rot, override_gravity, slide_move) in players.iter() {
-        let mut moved_to = *coords;
-        let mut new_rotation = *rot;
-        if input.direction == Direction::None {
-            continue;
-        }
-        let mut ground_rot = 0;
-        for &gravity_dir in
-            override_gravity.map_or([IVec2::new(0, -1)].as_slice(), |g| g.0.as_slice())
-        {
-            let mut new_coords = IVec2::from(*coords);
-            let move_dir = match input.direction {
-                Direction::Left => gravity_dir.rotate(IVec2::new(0, -1)),
-                Direction::None => {
-                    continue;
-                }
-                Direction::Right => gravity_dir.rotate(IVec2::new(0, 1)),
-            };
-            new_coords += move_dir;
-            let mut new_coords = new_coords.into();
-            let mut ignore_block = false;
-            if let Some((pushed_entity, _coords, pushed_rot)) = pushable
-                .iter()
-                .find(|&(_entity, &coords, _rot)| coords == new_coords)
-            {
-                let new_pushable_coords = (IVec2::from(new_coords) + move_dir).into();
-                if !is_blocked(new_pushable_coords, &blocked) {
-                    ignore_block = true;
-                    // TODO: this is wrong way of doing it
-                    events.send(turns::MoveEvent {
-                        player: pushed_entity,
-                        coords: new_pushable_coords,
-                        rotation: {
-                            let mut rot = *pushed_rot;
-                            // TODO this is also wrong
-                            match input.direction {
-                                Direction::Left => rot.rotate_left(),
-                                Direction::None => {
-                                    unreachable!()
-                                }
-                                Direction::Right => rot.rotate_right(),
-                            }
-                            rot
-                        },
-                        sfx: None,
-                        end_sfx: None,
-                        vfx: None,
-                        end_vfx: None,
-                    });
-                }
-            }
-            if !ignore_block && is_blocked(new_coords, &blocked) {
-                continue;
-            }
-            if override_gravity.is_some() {
-                let turn_corner_coords = (IVec2::from(new_coords) + gravity_dir).into();
-                if !is_blocked(turn_corner_coords, &blocked) {
-                    new_coords = turn_corner_coords;
-                    match input.direction {
-                        Direction::Left => new_rotation.rotate_left(),
-                        Direction::None => {
-                            unreachable!()
-                        }
-                        Direction::Right => new_rotation.rotate_right(),
-                    }
-                }
-            }
-            moved_to = new_coords;
-            ground_rot = vec_to_rot(gravity_dir);
-            break;
-        }
-        if slide_move.is_none() || moved_to.x == coords.x {
-            match input.direction {
-                Direction::Left => new_rotation.rotate_left(),
-                Direction::None => {
-                    unreachable!()
-                }
-                Direction::Right => new_rotation.rotate_right(),
-            }
-            events.send(turns::MoveEvent {
-                player,
-                coords: moved_to,
-                rotation: new_rotation,
-                sfx: Some(if override_gravity.is_some() {
-                    "sfx/magnet.wav"
-                } else {
-                    "sfx/move.wav"
-                }),
-                end_sfx: None,
-                vfx: Some(VfxBundle::new(
-                    *coords,
-                    ground_rot,
-                    "animation/walk.png",
-                    None,
-                    false,
-                    input.direction == Direction::Left,
-                )),
-                end_vfx: None,
-            });
-            next_state.set(turns::State::Animation);
-        } else {
-            next_state.set(turns::State::Turn);
-        }
+    if players.is_empty() || (actions.switch_player == 0 && !selected.is_empty()) {
+        return;
     }
+    let Some(coords) = sim.switch_player(actions.switch_player) else {
+        return;
+    };
+    audio.play_sfx(asset_server.load("sfx/selectPlayer.wav"));
+    commands.spawn(VfxBundle::new(
+        coords,
+        0,
+        "animation/PLAYER_CHANGE.png",
+        None,
+        true,
+        false,
+    ));
 }
 
-pub fn falling_system(
-    blocked: Query<BlockedQuery, With<Blocking>>,
-    players: Query<
-        (
-            Entity,
-            &GridCoords,
-            &Rotation,
-            Option<&player::OverrideGravity>,
-        ),
-        With<Movable>,
-    >,
+/// Entities whose turn is held down, by a magnet or a slide power
+type Held = Or<(With<OverrideGravity>, With<SlideMove>)>;
+
+pub fn move_system(
+    mut next_state: ResMut<NextState<turns::State>>,
+    players: Query<&Input, With<SelectedPlayer>>,
+    held: Query<(), Held>,
+    mut sim: sync::SimWorld,
     mut events: EventWriter<turns::MoveEvent>,
 ) {
-    for (player, coords, rotation, override_gravity) in players.iter() {
-        for &gravity in override_gravity.map_or([IVec2::new(0, -1)].as_slice(), |g| g.0.as_slice())
-        {
-            let new_coords = (IVec2::from(*coords) + gravity).into();
-            if !is_blocked(new_coords, &blocked) {
-                events.send(turns::MoveEvent {
-                    player,
-                    coords: new_coords,
-                    rotation: *rotation,
-                    sfx: None,
-                    end_sfx: None,
-                    vfx: None,
-                    end_vfx: None,
-                });
+    for input in players.iter() {
+        if input.direction == Direction::None {
+            // Nothing pressed, the turn is no longer held down
+            if !held.is_empty() {
+                sim.step(sim::Input::Move(Direction::None));
             }
+            continue;
         }
+        let moves = sim.step(sim::Input::Move(input.direction));
+        // Sliding does not animate the move itself, the turn does
+        next_state.set(if moves.is_empty() {
+            turns::State::Turn
+        } else {
+            turns::State::Animation
+        });
+        events.send_batch(moves);
     }
 }
@@ -0,0 +1,341 @@
+//! Recording and playback of player inputs
+//!
+//! Run the game with `--record <file>` to write every input into a replay file,
+//! and with `--play <file>` to feed them back instead of the keyboard.
+//! Actions made in the same frame are tagged with the turn the frame started in
+//! and are played back in a single frame again.
+//! Level restarts are detected by the turn number going back.
+//! Nothing is recorded or played before a player is selected, the input of that frame is lost.
+//! Undo and redo are recorded as well, they bring the turn number back with the state.
+//! The first frame with nothing pressed after a move is recorded as [Direction::None],
+//! moves that follow the turn right away keep its magnets and slide powers.
+
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+};
+
+use bevy::ecs::system::SystemParam;
+use serde::{Deserialize, Serialize};
+
+use super::{player::PlayerActions, *};
+
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Mode>();
+        app.init_resource::<Turn>();
+        app.add_startup_system(setup);
+        app.add_system(level_started);
+        app.add_system(
+            record
+                .run_if(
+                    resource_exists::<Recording>()
+                        .and_then(player::accepts_input)
+                        .and_then(player::player_selected),
+                )
+                .after(player::keyboard_input)
+                .before(history::UndoRedo)
+                .before(player::update_player_input),
+        );
+        app.add_system(
+            play.run_if(
+                resource_exists::<Playback>()
+                    .and_then(player::accepts_input)
+                    .and_then(player::player_selected),
+            )
+            .after(player::keyboard_input)
+            .before(history::UndoRedo)
+            .before(player::update_player_input),
+        );
+    }
+}
+
+#[derive(Resource, Default, Debug, Clone)]
+pub enum Mode {
+    #[default]
+    Off,
+    Record(PathBuf),
+    Play(PathBuf),
+}
+
+impl Mode {
+    /// Parses `--record <file>` or `--play <file>`
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Self {
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mode: fn(PathBuf) -> Self = match arg.as_str() {
+                "--record" => Self::Record,
+                "--play" => Self::Play,
+                _ => continue,
+            };
+            return mode(args.next().expect("Replay file expected").into());
+        }
+        Self::Off
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Action {
+    /// Either [sim::Input::Move] or [sim::Input::SwitchPlayer]
+    Input(sim::Input),
+    /// Goes back to the previous state, see [history](super::history)
+    Undo,
+    /// Goes forward to the state that was undone
+    Redo,
+}
+
+impl Action {
+    /// Whether the action makes a move, counted by [Turn]
+    fn is_move(self) -> bool {
+        matches!(self, Self::Input(sim::Input::Move(direction)) if direction != Direction::None)
+    }
+
+    /// Order of the actions of a single frame, see [record]
+    fn frame_order(self) -> u8 {
+        match self {
+            Self::Undo | Self::Redo => 0,
+            Self::Input(sim::Input::Move(_) | sim::Input::Wait) => 1,
+            Self::Input(sim::Input::SwitchPlayer(_)) => 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReplayInput {
+    /// Identifier of the level in ldtk
+    pub level: String,
+    /// [Turn] the frame of the action started in
+    pub turn: u32,
+    pub action: Action,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Replay {
+    pub version: u32,
+    pub inputs: Vec<ReplayInput>,
+}
+
+impl Default for Replay {
+    fn default() -> Self {
+        Self {
+            version: Self::VERSION,
+            inputs: Vec::new(),
+        }
+    }
+}
+
+impl Replay {
+    /// Bump this when the format changes
+    pub const VERSION: u32 = 1;
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let json = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let replay: Self = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+        if replay.version != Self::VERSION {
+            return Err(format!(
+                "Replay version is {}, expected {}",
+                replay.version,
+                Self::VERSION,
+            ));
+        }
+        Ok(replay)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(path, json).map_err(|e| e.to_string())
+    }
+}
+
+/// Moves made since the level was (re)started, undo and redo set it to the turn they go to
+#[derive(Resource, Default)]
+pub struct Turn(pub u32);
+
+#[derive(Resource)]
+struct Recording {
+    path: PathBuf,
+    replay: Replay,
+    /// Nothing was pressed since the last move, or the level just started
+    idle: bool,
+}
+
+#[derive(Resource)]
+struct Playback {
+    inputs: VecDeque<ReplayInput>,
+    /// Level was changed or restarted, but is not spawned yet
+    waiting_for_level: bool,
+}
+
+#[derive(SystemParam)]
+struct CurrentLevel<'w, 's> {
+    levels: Query<'w, 's, &'static Handle<LdtkLevel>>,
+    assets: Res<'w, Assets<LdtkLevel>>,
+}
+
+impl CurrentLevel<'_, '_> {
+    fn identifier(&self) -> Option<String> {
+        let level = self.levels.get_single().ok()?;
+        Some(self.assets.get(level)?.level.identifier.clone())
+    }
+}
+
+fn setup(mode: Res<Mode>, mut commands: Commands) {
+    match &*mode {
+        Mode::Off => {}
+        Mode::Record(path) => {
+            info!("Recording replay to {path:?}");
+            commands.insert_resource(Recording {
+                path: path.clone(),
+                replay: Replay::default(),
+                idle: true,
+            });
+        }
+        Mode::Play(path) => {
+            let replay = Replay::load(path)
+                .unwrap_or_else(|e| panic!("Failed to load replay {path:?}: {e}"));
+            info!("Playing replay {path:?}");
+            commands.insert_resource(Playback {
+                inputs: replay.inputs.into(),
+                waiting_for_level: false,
+            });
+        }
+    }
+}
+
+fn level_started(
+    levels: Query<(), Added<Handle<LdtkLevel>>>,
+    mut turn: ResMut<Turn>,
+    playback: Option<ResMut<Playback>>,
+    recording: Option<ResMut<Recording>>,
+) {
+    if !levels.is_empty() {
+        turn.0 = 0;
+        if let Some(mut playback) = playback {
+            playback.waiting_for_level = false;
+        }
+        if let Some(mut recording) = recording {
+            recording.idle = true;
+        }
+    }
+}
+
+fn record(
+    actions: Res<PlayerActions>,
+    state: Res<State<turns::State>>,
+    mut turn: ResMut<Turn>,
+    level: CurrentLevel,
+    mut recording: ResMut<Recording>,
+) {
+    let Some(level) = level.identifier() else {
+        return;
+    };
+    let waiting = state.0 == turns::State::WaitingForInput;
+    let mut actions_made = Vec::new();
+    // Undo is done before the input of the same frame, only undo is possible after failing
+    if actions.undo {
+        actions_made.push(Action::Undo);
+    } else if actions.redo && waiting {
+        actions_made.push(Action::Redo);
+    }
+    if waiting {
+        // A switch always comes with the direction held on its frame, see [sim::play_frame]
+        if actions.direction != Direction::None || !recording.idle || actions.switch_player != 0 {
+            actions_made.push(Action::Input(sim::Input::Move(actions.direction)));
+        }
+        recording.idle = actions.direction == Direction::None;
+        if actions.switch_player != 0 {
+            let input = sim::Input::SwitchPlayer(actions.switch_player);
+            actions_made.push(Action::Input(input));
+        }
+    }
+    if actions_made.is_empty() {
+        return;
+    }
+    let frame_turn = turn.0;
+    for action in actions_made {
+        recording.replay.inputs.push(ReplayInput {
+            level: level.clone(),
+            turn: frame_turn,
+            action,
+        });
+        if action.is_move() {
+            turn.0 += 1;
+        }
+    }
+    if let Err(e) = recording.replay.save(&recording.path) {
+        error!("Failed to save replay: {e}");
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn play(
+    mut actions: ResMut<PlayerActions>,
+    mut turn: ResMut<Turn>,
+    level: CurrentLevel,
+    mut playback: ResMut<Playback>,
+    mut level_selection: ResMut<LevelSelection>,
+    worlds: Query<(Entity, &Handle<LdtkAsset>)>,
+    ldtk_assets: Res<Assets<LdtkAsset>>,
+    mut commands: Commands,
+) {
+    // Keyboard is ignored while playing
+    *actions = PlayerActions::default();
+    if playback.waiting_for_level {
+        return;
+    }
+    let Some(current_level) = level.identifier() else {
+        return;
+    };
+    let Some(next) = playback.inputs.front() else {
+        info!("Replay finished");
+        commands.remove_resource::<Playback>();
+        return;
+    };
+    let (world, ldtk) = worlds.single();
+    if next.level != current_level {
+        // Replay starts at another level, or levels were skipped with cheats
+        let index = ldtk_assets
+            .get(ldtk)
+            .unwrap()
+            .iter_levels()
+            .position(|level| level.identifier == next.level)
+            .unwrap_or_else(|| panic!("Level {:?} from the replay not found", next.level));
+        if *level_selection != LevelSelection::Index(index) {
+            *level_selection = LevelSelection::Index(index);
+        }
+        playback.waiting_for_level = true;
+        return;
+    }
+    if next.turn < turn.0 {
+        info!("Restarting the level like in the replay");
+        commands.entity(world).insert(Respawn);
+        playback.waiting_for_level = true;
+        return;
+    }
+    // Everything recorded in one frame, the order of the actions tells frames of the same turn apart
+    let frame_turn = next.turn;
+    let mut last_order = None;
+    while let Some(next) = playback.inputs.front() {
+        let order = next.action.frame_order();
+        if next.level != current_level
+            || next.turn != frame_turn
+            || last_order.is_some_and(|last| order <= last)
+        {
+            break;
+        }
+        last_order = Some(order);
+        match playback.inputs.pop_front().unwrap().action {
+            Action::Input(sim::Input::Move(Direction::None)) => {}
+            Action::Input(sim::Input::Move(direction)) => {
+                actions.direction = direction;
+                turn.0 += 1;
+            }
+            Action::Input(sim::Input::SwitchPlayer(dir)) => actions.switch_player = dir,
+            Action::Input(sim::Input::Wait) => {}
+            Action::Undo => actions.undo = true,
+            Action::Redo => actions.redo = true,
+        }
+    }
+}
@@ -1,26 +1,23 @@
-use super::{player::Movable, turns::TurnOrder, *};
-use std::{f32::consts::PI, marker::PhantomData};
-
-mod jump;
-mod magnet;
-mod slide;
+use super::*;
+use std::f32::consts::PI;
 
 pub struct Plugin;
 
 impl bevy::app::Plugin for Plugin {
     fn build(&self, app: &mut App) {
-        app.add_plugin(jump::Plugin);
-        app.add_plugin(slide::Plugin);
-        app.add_plugin(magnet::Plugin);
-
         app.add_system(side_init);
 
+        // Powers are only known once world.powers.json is loaded, so every other entity may be one
+        app.register_default_ldtk_entity::<PowerEntityBundle>();
+        app.add_system(init_power_entities);
+
         app.register_ldtk_entity::<DevNullBundle>("DevNull");
+        app.register_ldtk_entity::<PortalBundle>("Portal");
     }
 }
 
 #[derive(Debug, Component)]
-pub struct Side(i32);
+pub struct Side(pub i32);
 
 #[derive(Component)]
 pub struct Blank;
@@ -31,7 +28,11 @@ pub struct Trigger;
 #[derive(Default, Component)]
 pub struct PickupSideEffects;
 
-fn side_init(query: Query<Entity, Added<PickupSideEffects>>, mut commands: Commands) {
+fn side_init(
+    query: Query<Entity, Added<PickupSideEffects>>,
+    tile_size: Res<level::TileSize>,
+    mut commands: Commands,
+) {
     for player in query.iter() {
         for i in 0..4 {
             commands
@@ -40,10 +41,7 @@ fn side_init(query: Query<Entity, Added<PickupSideEffects>>, mut commands: Comma
                     Blank,
                     TransformBundle::from_transform(
                         Transform::from_rotation(Quat::from_rotation_z(-(i + 2) as f32 * PI / 2.0))
-                            * Transform::from_translation(Vec3::new(
-                                0.0, 16.0, // KEKW
-                                0.0,
-                            )),
+                            * Transform::from_translation(Vec3::new(0.0, tile_size.y as f32, 0.0)),
                     ),
                     VisibilityBundle::default(),
                 ))
@@ -73,220 +71,123 @@ struct DevNullBundle {
     name: Name,
 }
 
-trait SideEffect: Component + Default {
-    fn active_below() -> bool {
-        true
-    }
-    fn active_side() -> bool {
-        false
-    }
-    fn active_above() -> bool {
-        false
-    }
-}
-
-#[derive(Debug)]
-struct SideEffectEvent<T: SideEffect> {
-    player: Entity,
-    side: i32,
-    phantom_data: PhantomData<T>,
-}
-
-fn detect_wall_side_effect<T: SideEffect>(
-    players: Query<(Entity, &GridCoords, &Rotation), (With<Movable>, With<Trigger>)>,
-    effects: Query<(&GridCoords, &Rotation), (With<WallEffect>, With<T>)>,
-    mut events: EventWriter<SideEffectEvent<T>>,
-) {
-    for (player, player_coords, player_rot) in &players {
-        for (effect_coords, effect_rot) in &effects {
-            if player_coords != effect_coords {
-                continue;
-            }
-            info!("Hello? I am wall effect");
-            events.send(SideEffectEvent {
-                player,
-                side: player_side(player_rot, -side_vec(effect_rot.0, 0)),
-                phantom_data: PhantomData,
-            });
-        }
-    }
+/// Tile on a wall that teleports to the other portal of the same pair, see [sim::EntityKind::Portal]
+#[derive(Component)]
+pub struct Portal {
+    pub pair: i32,
 }
 
-fn detect_side_effect<T: SideEffect>(
-    sides: Query<&Side, With<T>>,
-    players: Query<(Entity, &GridCoords, &Rotation, &Children), With<Movable>>,
-    blocked: Query<BlockedQuery, With<Trigger>>,
-    mut events: EventWriter<SideEffectEvent<T>>,
-) {
-    info!("CHECKING FOR ACTIVATION");
-    for (player, player_coords, player_rotation, player_children) in players.iter() {
-        let mut sides: Vec<&Side> = player_children
-            .iter()
-            .flat_map(|&child| sides.get(child).ok())
-            .collect();
-        sides.sort_by_key(|side| -side_vec(player_rotation.0, side.0).y);
-        for side in sides {
-            info!("I HAVE POWER POG");
-            if !match side_vec(player_rotation.0, side.0) {
-                IVec2 { y: -1, .. } => T::active_below(),
-                IVec2 { y: 1, .. } => T::active_above(),
-                IVec2 { y: 0, .. } => T::active_side(),
-                _ => unreachable!(),
-            } {
-                continue;
-            }
-            let direction = side_vec(player_rotation.0, side.0);
-            let side_coords = (IVec2::from(*player_coords) + direction).into();
-            if is_blocked(side_coords, &blocked) {
-                info!("ACTIVATED");
-                events.send(SideEffectEvent {
-                    player,
-                    side: side.0,
-                    phantom_data: PhantomData,
-                });
-                if let Some((other_player, _, other_player_rot, ..)) = players
-                    .iter()
-                    .find(|&(_, coords, ..)| *coords == side_coords)
-                {
-                    events.send(SideEffectEvent {
-                        player: other_player,
-                        side: player_side(other_player_rot, direction),
-                        phantom_data: PhantomData,
-                    });
-                }
-            }
+impl From<&EntityInstance> for Portal {
+    fn from(entity: &EntityInstance) -> Self {
+        Self {
+            pair: sim::portal_pair(entity),
         }
     }
 }
 
 #[derive(Bundle, LdtkEntity)]
-struct WallEffectBundle<T: 'static + Send + Sync + Component + Default> {
+struct PortalBundle {
     #[sprite_sheet_bundle]
     sprite_sheet: SpriteSheetBundle,
     #[grid_coords]
     position: GridCoords,
-    effect: T,
+    #[from_entity_instance]
+    portal: Portal,
     #[from_entity_instance]
     rotation: Rotation,
-    wall_effect: WallEffect,
     #[with(entity_name)]
     name: Name,
 }
 
+/// Present while [sim::EntityState::in_portal] is set
+#[derive(Component)]
+pub struct InPortal;
+
+/// Present while [sim::EntityState::turned] is set
+#[derive(Component)]
+pub struct Turned;
+
+/// Group of entities stuck together by glue, see [sim::EntityState::group]
+#[derive(Debug, Component, Clone, Copy)]
+pub struct Glued(pub u32);
+
+/// Power of a powerup, wall effect or a side, see [sim::PowerDef]
+#[derive(Debug, Component, Clone, Copy)]
+pub struct SidePower(pub sim::Power);
+
+/// Looping sound of a sliding entity, present while [sim::EntityState::sliding] is set
+#[derive(Component)]
+pub struct SlideSfx(pub Handle<AudioSink>);
+
+/// Entity that is not registered by name, see [init_power_entities]
+#[derive(Default, Component)]
+struct PowerEntity;
+
+/// Powerup, wall effect or just a decoration, told apart by [init_power_entities]
 #[derive(Bundle, LdtkEntity)]
-struct PowerupBundle<T: 'static + Send + Sync + Component + Default> {
+struct PowerEntityBundle {
     #[sprite_sheet_bundle]
     sprite_sheet: SpriteSheetBundle,
     #[grid_coords]
     position: GridCoords,
-    effect: T,
+    power_entity: PowerEntity,
     #[from_entity_instance]
     rotation: Rotation,
-    powerup: side::Powerup,
+    #[from_entity_instance]
+    entity_instance: EntityInstance,
     #[with(entity_name)]
     name: Name,
 }
 
-trait AppExt {
-    fn register_side_effect<T: SideEffect>(&mut self, ldtk_name: &str);
-}
-
-impl AppExt for App {
-    fn register_side_effect<T: SideEffect>(&mut self, ldtk_name: &str) {
-        self.register_ldtk_entity::<PowerupBundle<T>>(ldtk_name);
-        self.register_ldtk_entity::<WallEffectBundle<T>>(&format!("{ldtk_name}Wall"));
-        self.add_turn_system(
-            collect_powerup::<T>.before(powerups_collected),
-            TurnOrder::CollectPowerups,
-        );
-        self.add_turn_system(
-            delete_side_effect::<T>.before(powerups_collected),
-            TurnOrder::CollectPowerups,
-        );
-        self.add_turn_system(
-            detect_wall_side_effect::<T>.after(powerups_collected),
-            TurnOrder::DetectSideEffect,
-        );
-        self.add_turn_system(
-            detect_side_effect::<T>.after(powerups_collected),
-            TurnOrder::DetectSideEffect,
-        );
-        self.add_event::<SideEffectEvent<T>>();
-    }
-}
-
-// This is here just for the sake of ordering
-fn powerups_collected() {}
-
-fn delete_side_effect<T: SideEffect>(
-    mut sides: Query<&Side, With<T>>,
-    players: Query<(&GridCoords, &Rotation, &Children), With<Movable>>,
-    devnulls: Query<(Entity, &GridCoords), With<DevNull>>,
+/// Entities named like the power are [Powerup]s, with `Wall` appended [WallEffect]s
+///
+/// Anything else is only decoration, like [sim::EntityKind::from_ldtk] skips it.
+fn init_power_entities(
+    query: Query<(Entity, &EntityInstance), Added<PowerEntity>>,
     mut commands: Commands,
-    audio: Res<Audio>,
-    asset_server: Res<AssetServer>,
 ) {
-    for (player_coords, player_rotation, player_children) in players.iter() {
-        for (devnull, devnull_coords) in devnulls.iter() {
-            if player_coords == devnull_coords {
-                for &side in player_children {
-                    if let Ok(side_data) = sides.get_mut(side) {
-                        if side_data.0 == player_rotation.0 {
-                            commands.entity(devnull).despawn();
-                            commands.entity(side).insert(Blank).remove::<T>();
-                            commands
-                                .entity(side)
-                                .remove::<TextureAtlasSprite>()
-                                .remove::<Handle<TextureAtlas>>();
-
-                            audio.play_sfx(asset_server.load("sfx/hitHurt.wav"));
-                        }
-                    }
-                }
-            }
+    for (entity, instance) in query.iter() {
+        let name = instance.identifier.as_str();
+        if let Some(power) = sim::Power::from_ldtk_name(name) {
+            commands.entity(entity).insert((SidePower(power), Powerup));
+        } else if let Some(power) = name
+            .strip_suffix("Wall")
+            .and_then(sim::Power::from_ldtk_name)
+        {
+            commands
+                .entity(entity)
+                .insert((SidePower(power), WallEffect));
         }
     }
 }
 
-#[allow(clippy::type_complexity)]
-fn collect_powerup<T: SideEffect>(
-    mut sides: Query<&Side, With<Blank>>,
-    players: Query<(&GridCoords, &Rotation, &Children), With<PickupSideEffects>>,
-    powerups: Query<
-        (
-            Entity,
-            &GridCoords,
-            &Rotation,
-            &TextureAtlasSprite,
-            &Handle<TextureAtlas>,
-        ),
-        (With<Powerup>, With<T>),
-    >,
-    mut commands: Commands,
-    audio: Res<Audio>,
-    asset_server: Res<AssetServer>,
-) {
-    for (player_coords, player_rotation, player_children) in players.iter() {
-        for (powerup, powerup_coords, powerup_rotation, sprite, atlas) in powerups.iter() {
-            if player_coords == powerup_coords {
-                for &side in player_children {
-                    if let Ok(side_data) = sides.get_mut(side) {
-                        if (side_data.0 - player_rotation.0 + powerup_rotation.0) % 4 == 0 {
-                            commands.entity(powerup).despawn();
-                            commands.entity(side).remove::<Blank>().insert(T::default());
-
-                            commands
-                                .entity(side)
-                                .insert(sprite.clone())
-                                .insert(atlas.clone());
-
-                            audio.play_sfx(asset_server.load("sfx/powerUp.wav"));
+/// Reads which power an entity (powerup, wall effect or a side) has
+#[derive(WorldQuery)]
+pub struct PowerQuery {
+    power: Option<&'static SidePower>,
+}
 
-                            info!("COLLECTED");
-                        }
-                    }
-                }
-            }
-        }
+impl PowerQueryItem<'_> {
+    pub fn power(&self) -> Option<sim::Power> {
+        self.power.map(|power| power.0)
     }
 }
+
+/// Replaces the power of a side, `sprite` is taken from the collected powerup
+pub fn set_side_power(
+    commands: &mut Commands,
+    side: Entity,
+    power: Option<sim::Power>,
+    sprite: Option<(TextureAtlasSprite, Handle<TextureAtlas>)>,
+) {
+    let mut side = commands.entity(side);
+    side.remove::<(Blank, SidePower)>();
+    match power {
+        None => side.insert(Blank),
+        Some(power) => side.insert(SidePower(power)),
+    };
+    match sprite {
+        Some(sprite) => side.insert(sprite),
+        None => side.remove::<(TextureAtlasSprite, Handle<TextureAtlas>)>(),
+    };
+}
@@ -0,0 +1,88 @@
+//! Buttons, plates and doors, they are pressed and opened in the [sim]
+
+use super::{level::Blocking, *};
+
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.register_ldtk_entity::<SignalBundle>("Button");
+        app.register_ldtk_entity::<SignalBundle>("Plate");
+        app.register_ldtk_entity::<DoorBundle>("Door");
+        app.add_system(update_sprites);
+    }
+}
+
+/// Button, plate or door, linked to the others with the same channel
+#[derive(Debug, Component, Clone, Copy)]
+pub enum Signal {
+    Button(i32),
+    Plate(i32),
+    Door(i32),
+}
+
+impl From<&EntityInstance> for Signal {
+    fn from(entity: &EntityInstance) -> Self {
+        let channel = sim::signal_channel(entity);
+        match entity.identifier.as_str() {
+            "Button" => Self::Button(channel),
+            "Plate" => Self::Plate(channel),
+            "Door" => Self::Door(channel),
+            name => panic!("{name:?} is not a signal"),
+        }
+    }
+}
+
+impl From<Signal> for sim::EntityKind {
+    fn from(signal: Signal) -> Self {
+        match signal {
+            Signal::Button(channel) => Self::Button(channel),
+            Signal::Plate(channel) => Self::Plate(channel),
+            Signal::Door(channel) => Self::Door(channel),
+        }
+    }
+}
+
+/// Pressed button or plate, open door, present while [sim::EntityState::active] is set
+#[derive(Component)]
+pub struct Active;
+
+#[derive(Bundle, LdtkEntity)]
+struct SignalBundle {
+    #[sprite_sheet_bundle]
+    sprite_sheet: SpriteSheetBundle,
+    #[grid_coords]
+    position: GridCoords,
+    #[from_entity_instance]
+    signal: Signal,
+    #[with(entity_name)]
+    name: Name,
+}
+
+/// Doors start closed, [Blocking] is removed while they are open
+#[derive(Bundle, LdtkEntity)]
+struct DoorBundle {
+    #[sprite_sheet_bundle]
+    sprite_sheet: SpriteSheetBundle,
+    #[grid_coords]
+    position: GridCoords,
+    #[from_entity_instance]
+    signal: Signal,
+    blocking: Blocking,
+    #[with(entity_name)]
+    name: Name,
+}
+
+/// Open doors fade out, pressed buttons and plates get darker
+fn update_sprites(mut query: Query<(&Signal, Option<&Active>, &mut TextureAtlasSprite)>) {
+    for (signal, active, mut sprite) in query.iter_mut() {
+        let color = match (signal, active.is_some()) {
+            (_, false) => Color::WHITE,
+            (Signal::Door(_), true) => Color::rgba(1.0, 1.0, 1.0, 0.3),
+            (_, true) => Color::GRAY,
+        };
+        if sprite.color != color {
+            sprite.color = color;
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use super::*;
+
+/// Canonical form of an entity, see [CanonicalState]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct CanonicalEntity {
+    pub kind: EntityKind,
+    pub coords: [i32; 2],
+    /// Always in `0..4`
+    pub rotation: i32,
+    pub sides: [Option<Power>; 4],
+    pub input: Direction,
+    pub override_gravity: Vec<[i32; 2]>,
+    pub slide_move: bool,
+    pub sliding: bool,
+    pub push_motion: PushMotion,
+    pub in_portal: bool,
+    pub fall_distance: i32,
+    pub bouncing: bool,
+    pub turned: bool,
+    pub active: bool,
+    /// Smallest coords of the group members, group ids themselves do not matter
+    pub group: Option<[i32; 2]>,
+}
+
+impl From<&EntityState> for CanonicalEntity {
+    fn from(entity: &EntityState) -> Self {
+        Self {
+            kind: entity.kind,
+            coords: [entity.coords.x, entity.coords.y],
+            rotation: entity.rotation.normalized().0,
+            sides: entity.sides,
+            input: entity.input,
+            override_gravity: entity
+                .override_gravity
+                .iter()
+                .map(|gravity| gravity.to_array())
+                .collect(),
+            slide_move: entity.slide_move,
+            sliding: entity.sliding,
+            push_motion: entity.push_motion,
+            in_portal: entity.in_portal,
+            fall_distance: entity.fall_distance,
+            bouncing: entity.bouncing,
+            turned: entity.turned,
+            active: entity.active,
+            group: None,
+        }
+    }
+}
+
+/// Form of a [LevelState] that is the same for states that play the same
+///
+/// Rotations are normalized and entities are sorted, so neither [EntityId]s
+/// nor the order of spawning matter. Level geometry is not included.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CanonicalState {
+    pub entities: Vec<CanonicalEntity>,
+    /// Index of the selected player in `entities`
+    pub selected: Option<usize>,
+    pub completed: bool,
+    pub failed: bool,
+}
+
+impl CanonicalState {
+    /// FNV-1a of the serialized form, unlike [std::hash::Hash] it is the same across builds
+    pub fn stable_hash(&self) -> u64 {
+        let json = serde_json::to_vec(self).expect("Failed to serialize state");
+        json.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        })
+    }
+}
+
+impl LevelState {
+    pub fn canonical(&self) -> CanonicalState {
+        let mut entities: Vec<(CanonicalEntity, EntityId)> = self
+            .iter()
+            .map(|(id, entity)| {
+                let mut canonical = CanonicalEntity::from(entity);
+                if entity.group.is_some() {
+                    canonical.group = self
+                        .group_of(id)
+                        .iter()
+                        .map(|&member| {
+                            let coords = self.get(member).unwrap().coords;
+                            [coords.x, coords.y]
+                        })
+                        .min();
+                }
+                (canonical, id)
+            })
+            .collect();
+        entities.sort();
+        CanonicalState {
+            selected: self
+                .selected
+                .and_then(|selected| entities.iter().position(|&(_, id)| id == selected)),
+            entities: entities.into_iter().map(|(entity, _)| entity).collect(),
+            completed: self.completed,
+            failed: self.failed.is_some(),
+        }
+    }
+}
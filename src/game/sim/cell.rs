@@ -0,0 +1,80 @@
+use std::sync::OnceLock;
+
+use super::*;
+
+/// What a cell of the IntGrid layer does, looked up in `assets/world.cells.json`, see [CellTable]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(default)]
+pub struct Cell {
+    pub blocking: bool,
+    /// Activates side effects, see [LevelState::is_trigger]
+    pub trigger: bool,
+    /// Blocks moving and falling down into it, not coming from below or the sides
+    pub one_way: bool,
+    /// Entities on it move sideways without rolling
+    pub slippery: bool,
+    /// Entities next to it stick to it like to a magnet, see [EntityState::override_gravity]
+    pub sticky: bool,
+    /// Launches entities falling on it back, see [EntityState::fall_distance]
+    pub spring: bool,
+    /// A player touching it fails the level
+    pub hazard: bool,
+}
+
+impl Cell {
+    /// Whether moving by `dir` into the cell is blocked
+    pub fn blocks(self, dir: IVec2) -> bool {
+        self.blocking || (self.one_way && dir.y < 0)
+    }
+}
+
+/// A named set of [Cell] flags, matched against the identifiers of IntGrid values
+#[derive(Debug, Clone, Deserialize)]
+pub struct CellDef {
+    pub identifier: String,
+    #[serde(flatten)]
+    pub cell: Cell,
+}
+
+static DEFS: OnceLock<Vec<CellDef>> = OnceLock::new();
+
+impl CellDef {
+    /// Definitions set by [CellDef::init], panics before that
+    pub fn all() -> &'static [CellDef] {
+        DEFS.get().expect("world.cells.json is not loaded")
+    }
+
+    /// Sets the definitions once, like [PowerDef::init]
+    pub fn init(defs: Vec<CellDef>) -> bool {
+        DEFS.set(defs).is_ok()
+    }
+}
+
+/// Identifiers of the IntGrid values of a project, the same in every IntGrid layer
+#[derive(Debug, Clone, Default)]
+pub struct CellTable {
+    identifiers: HashMap<i32, String>,
+}
+
+impl CellTable {
+    pub fn insert(&mut self, value: i32, identifier: String) {
+        self.identifiers.insert(value, identifier);
+    }
+
+    /// Fails for values without an identifier or with one that has no [CellDef]
+    ///
+    /// The slope values of `world.ldtk` have none, the sim has no slopes.
+    pub fn get(&self, value: i32) -> Result<Cell, String> {
+        let identifier = self
+            .identifiers
+            .get(&value)
+            .ok_or_else(|| format!("IntGrid value {value} has no identifier"))?;
+        CellDef::all()
+            .iter()
+            .find(|def| def.identifier == *identifier)
+            .map(|def| def.cell)
+            .ok_or_else(|| {
+                format!("IntGrid value {value} ({identifier:?}) is not defined in world.cells.json")
+            })
+    }
+}
@@ -0,0 +1,41 @@
+use super::*;
+
+/// Movables resting on a [EntityKind::Conveyor] are carried a cell along it
+///
+/// Belts only carry along the ground, one pointing along the gravity of an entity does nothing.
+pub fn carry(state: &LevelState, events: &mut Vec<MoveEvent>) {
+    for (_, conveyor) in state.iter() {
+        if conveyor.kind != EntityKind::Conveyor {
+            continue;
+        }
+        let dir = side_vec(conveyor.rotation.0, 0);
+        for (id, entity) in state.at(conveyor.coords) {
+            if !entity.kind.movable() || entity.group.is_some() {
+                continue;
+            }
+            let gravity = entity.gravity();
+            let resting = gravity.iter().all(|&gravity| {
+                state.is_blocked((IVec2::from(entity.coords) + gravity).into(), gravity)
+            });
+            if !resting || gravity.iter().any(|gravity| gravity.dot(dir) != 0) {
+                continue;
+            }
+            let coords = (IVec2::from(entity.coords) + dir).into();
+            if state.is_blocked(coords, dir) {
+                continue;
+            }
+            events.push(MoveEvent {
+                entity: id,
+                kind: MoveKind::Carry,
+                coords,
+                rotation: entity.rotation,
+                sfx: None,
+                end_sfx: None,
+                vfx: None,
+                end_vfx: None,
+                teleport: false,
+                input: None,
+            });
+        }
+    }
+}
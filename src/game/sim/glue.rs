@@ -0,0 +1,207 @@
+use super::*;
+
+impl LevelState {
+    /// Entities glued together with `id`, itself included
+    pub fn group_of(&self, id: EntityId) -> Vec<EntityId> {
+        match self.get(id).and_then(|entity| entity.group) {
+            None => vec![id],
+            Some(group) => self
+                .iter()
+                .filter(|(_, entity)| entity.group == Some(group))
+                .map(|(id, _)| id)
+                .collect(),
+        }
+    }
+
+    /// Members of every group
+    pub fn groups(&self) -> Vec<Vec<EntityId>> {
+        let mut groups: Vec<u32> = self.iter().filter_map(|(_, entity)| entity.group).collect();
+        groups.sort();
+        groups.dedup();
+        groups
+            .into_iter()
+            .map(|group| {
+                self.iter()
+                    .filter(|(_, entity)| entity.group == Some(group))
+                    .map(|(id, _)| id)
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Puts two entities and everything glued to them into one group
+    fn glue(&mut self, a: EntityId, b: EntityId) {
+        let group_a = self.get(a).and_then(|entity| entity.group);
+        let group_b = self.get(b).and_then(|entity| entity.group);
+        if group_a.is_some() && group_a == group_b {
+            return;
+        }
+        let group = group_a.or(group_b).unwrap_or_else(|| {
+            self.iter()
+                .filter_map(|(_, entity)| entity.group)
+                .max()
+                .map_or(0, |max| max + 1)
+        });
+        for id in self.ids() {
+            let entity = self.get_mut(id).unwrap();
+            if id == a || id == b || entity.group.is_some_and(|g| Some(g) == group_b) {
+                entity.group = Some(group);
+            }
+        }
+    }
+
+    /// Whether the group could move by `dir` into `coords`, its own members do not block it
+    fn fits(&self, group: &[EntityId], coords: GridCoords, dir: IVec2) -> bool {
+        !self.cell(coords).blocks(dir)
+            && self
+                .at(coords)
+                .all(|(id, other)| !other.occupancy().blocking || group.contains(&id))
+    }
+}
+
+/// [PowerDef::glue] sides stick to the movable entities they touch
+pub fn do_glue(state: &mut LevelState, effects: &[side::SideEffectEvent]) {
+    for effect in effects {
+        if !effect.power.def().glue {
+            continue;
+        }
+        let Some(entity) = state.get(effect.entity) else {
+            continue;
+        };
+        let side_coords =
+            (IVec2::from(entity.coords) + side_vec(entity.rotation.0, effect.side)).into();
+        if let Some(other) = state.find_at(side_coords, |other| other.kind.movable()) {
+            state.glue(effect.entity, other);
+        }
+    }
+}
+
+/// Moves the group of the selected player as one rigid body
+///
+/// The group rolls over the front bottom corner of its bounding box,
+/// or slides if the player is on a slide power. Pushes whatever is in the way.
+pub fn move_group(
+    state: &LevelState,
+    player: EntityId,
+    direction: Direction,
+    events: &mut Vec<MoveEvent>,
+) {
+    let group = state.group_of(player);
+    let entity = state.get(player).unwrap();
+    let gravity_dir = entity.gravity()[0];
+    let move_dir = match direction {
+        Direction::Left => gravity_dir.rotate(IVec2::new(0, -1)),
+        Direction::None => return,
+        Direction::Right => gravity_dir.rotate(IVec2::new(0, 1)),
+    };
+
+    let members: Vec<(EntityId, &EntityState)> = group
+        .iter()
+        .map(|&id| (id, state.get(id).unwrap()))
+        .collect();
+    let targets: Vec<(EntityId, GridCoords, Rotation)> = if entity.slide_move {
+        members
+            .iter()
+            .map(|&(id, member)| {
+                let coords = IVec2::from(member.coords) + move_dir;
+                (id, coords.into(), member.rotation)
+            })
+            .collect()
+    } else {
+        // Doubled coords, so that the corner of a cell is on the grid
+        let front = members
+            .iter()
+            .map(|(_, member)| IVec2::from(member.coords).dot(move_dir))
+            .max()
+            .unwrap();
+        let bottom = members
+            .iter()
+            .map(|(_, member)| IVec2::from(member.coords).dot(gravity_dir))
+            .max()
+            .unwrap();
+        let pivot = move_dir * (front * 2 + 1) + gravity_dir * (bottom * 2 + 1);
+        members
+            .iter()
+            .map(|&(id, member)| {
+                let offset = IVec2::from(member.coords) * 2 - pivot;
+                // Rolling turns the front into the bottom
+                let rolled =
+                    gravity_dir * offset.dot(move_dir) - move_dir * offset.dot(gravity_dir);
+                let coords = (pivot + rolled) / 2;
+                (id, coords.into(), member.rotation.rotated(direction))
+            })
+            .collect()
+    };
+
+    let mut pushed: Vec<EntityId> = Vec::new();
+    for &(_, coords, _) in &targets {
+        if state.fits(&group, coords, move_dir) {
+            continue;
+        }
+        let Some(chain) = player::push_chain(state, coords, move_dir) else {
+            return;
+        };
+        pushed.extend(chain);
+    }
+    pushed.sort();
+    pushed.dedup();
+
+    for pushed in pushed {
+        events.push(player::push_event(state, pushed, move_dir));
+    }
+    for (id, coords, rotation) in targets {
+        events.push(MoveEvent {
+            entity: id,
+            kind: MoveKind::Walk,
+            coords,
+            rotation,
+            sfx: (id == player).then_some("sfx/move.wav"),
+            end_sfx: None,
+            vfx: None,
+            end_vfx: None,
+            teleport: false,
+            input: None,
+        });
+    }
+}
+
+/// Groups fall as one when every member can fall in the same direction
+///
+/// A member attached to a wall by a magnet holds the whole group.
+pub fn group_falling(state: &LevelState, events: &mut Vec<MoveEvent>) {
+    for group in state.groups() {
+        let gravity = state
+            .get(group[0])
+            .unwrap()
+            .gravity()
+            .iter()
+            .find(|gravity| {
+                group
+                    .iter()
+                    .all(|&id| state.get(id).unwrap().gravity().contains(gravity))
+            });
+        let Some(&gravity) = gravity else { continue };
+        let falls = group.iter().all(|&id| {
+            let coords = IVec2::from(state.get(id).unwrap().coords) + gravity;
+            state.fits(&group, coords.into(), gravity)
+        });
+        if !falls {
+            continue;
+        }
+        for &id in &group {
+            let entity = state.get(id).unwrap();
+            events.push(MoveEvent {
+                entity: id,
+                kind: MoveKind::Fall,
+                coords: (IVec2::from(entity.coords) + gravity).into(),
+                rotation: entity.rotation,
+                sfx: None,
+                end_sfx: None,
+                vfx: None,
+                end_vfx: None,
+                teleport: false,
+                input: None,
+            });
+        }
+    }
+}
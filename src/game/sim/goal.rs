@@ -0,0 +1,96 @@
+use super::*;
+
+/// Which players have to reach a goal, from the `Objective` field of the level
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Objective {
+    /// A single player on a goal is enough
+    #[default]
+    AnyPlayer,
+    /// Every player has to be on a goal at the same time
+    AllPlayers,
+}
+
+/// Which players a [EntityKind::Goal] accepts, from its `Player` and `Rotation` fields
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct GoalRequirement {
+    /// [PlayerIndex](crate::game::PlayerIndex) of the only player accepted, any if not set
+    pub player: Option<i32>,
+    /// Rotation the player has to be in, in `0..4`, any if not set
+    pub rotation: Option<i32>,
+}
+
+impl Default for GoalRequirement {
+    /// Any player standing upright, like before goals had fields
+    fn default() -> Self {
+        Self {
+            player: None,
+            rotation: Some(0),
+        }
+    }
+}
+
+impl GoalRequirement {
+    pub fn accepts(self, entity: &EntityState) -> bool {
+        let EntityKind::Player(index) = entity.kind else {
+            return false;
+        };
+        self.player.is_none_or(|player| player == index)
+            && self
+                .rotation
+                .is_none_or(|rotation| rotation == entity.rotation.normalized().0)
+    }
+}
+
+impl LevelState {
+    /// Whether the player stands on a goal that accepts it
+    fn on_goal(&self, player: &EntityState) -> bool {
+        self.find_at(player.coords, |goal| {
+            matches!(goal.kind, EntityKind::Goal(requirement) if requirement.accepts(player))
+        })
+        .is_some()
+    }
+
+    /// Whether the level can be completed at all, it needs a goal or a target
+    pub fn has_objective(&self) -> bool {
+        self.iter()
+            .any(|(_, entity)| matches!(entity.kind, EntityKind::Goal(_) | EntityKind::Target))
+    }
+}
+
+/// The level is completed once its objective is met
+///
+/// Players have to reach the goals as the [Objective] of the level says,
+/// and every [EntityKind::Target] has to be covered by a pushable entity.
+/// A level without goals only needs its targets and the other way around.
+pub fn finish_level(state: &mut LevelState) {
+    if objective_met(state) {
+        state.completed = true;
+    }
+}
+
+fn objective_met(state: &LevelState) -> bool {
+    if !state.has_objective() {
+        return false;
+    }
+    let has_goals = state
+        .iter()
+        .any(|(_, goal)| matches!(goal.kind, EntityKind::Goal(_)));
+    let mut targets = state
+        .iter()
+        .filter(|(_, target)| target.kind == EntityKind::Target);
+    let targets_covered = targets.all(|(_, target)| {
+        state
+            .find_at(target.coords, |entity| entity.kind.pushable())
+            .is_some()
+    });
+
+    let mut players = state
+        .iter()
+        .filter(|(_, player)| matches!(player.kind, EntityKind::Player(_)));
+    let players_done = !has_goals
+        || match state.rules.objective {
+            Objective::AnyPlayer => players.any(|(_, player)| state.on_goal(player)),
+            Objective::AllPlayers => players.all(|(_, player)| state.on_goal(player)),
+        };
+    targets_covered && players_done
+}
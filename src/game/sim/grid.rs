@@ -0,0 +1,131 @@
+use std::{fmt::Debug, hash::Hash};
+
+use super::*;
+
+/// What an occupant does to the cell it is in
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Occupancy {
+    pub blocking: bool,
+    pub trigger: bool,
+    pub pushable: bool,
+    pub powerup: bool,
+    pub goal: bool,
+}
+
+impl Occupancy {
+    pub fn is_empty(self) -> bool {
+        self == Self::default()
+    }
+}
+
+/// Index of occupants by cell, so lookups don't have to scan every entity
+///
+/// Occupants of a cell are kept sorted, so lookups don't depend on the order of updates.
+#[derive(Debug, Clone)]
+pub struct Grid<T> {
+    cells: HashMap<GridCoords, Vec<(T, Occupancy)>>,
+    positions: HashMap<T, GridCoords>,
+}
+
+impl<T> Default for Grid<T> {
+    fn default() -> Self {
+        Self {
+            cells: HashMap::default(),
+            positions: HashMap::default(),
+        }
+    }
+}
+
+impl<T: Copy + Ord + Hash + Debug> Grid<T> {
+    /// Adds an occupant, or updates it if it is already in the grid
+    pub fn insert(&mut self, id: T, coords: GridCoords, occupancy: Occupancy) {
+        self.remove(id);
+        let cell = self.cells.entry(coords).or_default();
+        let index = cell.partition_point(|&(other, _)| other < id);
+        cell.insert(index, (id, occupancy));
+        self.positions.insert(id, coords);
+    }
+
+    pub fn remove(&mut self, id: T) -> Option<GridCoords> {
+        let coords = self.positions.remove(&id)?;
+        let cell = self.cells.get_mut(&coords).unwrap();
+        cell.retain(|&(other, _)| other != id);
+        if cell.is_empty() {
+            self.cells.remove(&coords);
+        }
+        Some(coords)
+    }
+
+    pub fn move_to(&mut self, id: T, coords: GridCoords) {
+        if let Some(occupancy) = self.occupancy(id) {
+            self.insert(id, coords, occupancy);
+        }
+    }
+
+    pub fn coords(&self, id: T) -> Option<GridCoords> {
+        self.positions.get(&id).copied()
+    }
+
+    pub fn occupancy(&self, id: T) -> Option<Occupancy> {
+        let coords = self.coords(id)?;
+        self.occupants(coords)
+            .find(|&(other, _)| other == id)
+            .map(|(_, occupancy)| occupancy)
+    }
+
+    pub fn occupants(&self, coords: GridCoords) -> impl Iterator<Item = (T, Occupancy)> + '_ {
+        self.cells.get(&coords).into_iter().flatten().copied()
+    }
+
+    /// First occupant of a cell matching the filter
+    pub fn find(&self, coords: GridCoords, filter: impl Fn(Occupancy) -> bool) -> Option<T> {
+        self.occupants(coords)
+            .find(|&(_, occupancy)| filter(occupancy))
+            .map(|(id, _)| id)
+    }
+
+    pub fn is_blocked(&self, coords: GridCoords) -> bool {
+        self.find(coords, |occupancy| occupancy.blocking).is_some()
+    }
+
+    pub fn is_trigger(&self, coords: GridCoords) -> bool {
+        self.find(coords, |occupancy| occupancy.trigger).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Checks that the index contains exactly the given occupants
+    pub fn check(
+        &self,
+        expected: impl IntoIterator<Item = (T, GridCoords, Occupancy)>,
+    ) -> Result<(), String> {
+        let mut count = 0;
+        for (id, coords, occupancy) in expected {
+            count += 1;
+            match self.coords(id) {
+                None => return Err(format!("{id:?} is missing")),
+                Some(actual) if actual != coords => {
+                    return Err(format!("{id:?} is at {actual:?} instead of {coords:?}"));
+                }
+                _ => {}
+            }
+            if self.occupancy(id) != Some(occupancy) {
+                return Err(format!("{id:?} has outdated occupancy"));
+            }
+        }
+        let cells_count: usize = self.cells.values().map(Vec::len).sum();
+        if count != self.len() || count != cells_count {
+            return Err(format!(
+                "Grid has {} occupants in {cells_count} cells, expected {count}",
+                self.len(),
+            ));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,25 @@
+use super::*;
+
+/// Players touching a [Cell::hazard] or below the level fail it, other entities below it are lost
+///
+/// Runs before anything moves, so that the player is seen touching the hazard first.
+pub fn check(state: &mut LevelState) {
+    for id in state.ids() {
+        let entity = state.get(id).unwrap();
+        if !entity.kind.movable() {
+            continue;
+        }
+        let out_of_bounds = entity.coords.y < 0;
+        let touching = (0..4).any(|side| {
+            let coords = (IVec2::from(entity.coords) + side_vec(0, side)).into();
+            state.cell(coords).hazard
+        });
+        match entity.kind {
+            EntityKind::Player(_) if out_of_bounds || touching => {
+                state.failed.get_or_insert(id);
+            }
+            _ if out_of_bounds => state.despawn(id),
+            _ => {}
+        }
+    }
+}
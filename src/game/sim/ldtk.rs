@@ -0,0 +1,198 @@
+use std::path::Path;
+
+use bevy_ecs_ldtk::{
+    ldtk::{self, FieldValue, Type},
+    utils::{int_grid_index_to_grid_coords, ldtk_grid_coords_to_grid_coords},
+};
+
+use super::*;
+use crate::game::PlayerIndex;
+
+impl CellTable {
+    pub fn from_ldtk(project: &ldtk::LdtkJson) -> Self {
+        let mut table = Self::default();
+        for layer in &project.defs.layers {
+            for value in &layer.int_grid_values {
+                if let Some(identifier) = &value.identifier {
+                    table.insert(value.value, identifier.clone());
+                }
+            }
+        }
+        table
+    }
+}
+
+impl EntityKind {
+    pub fn from_ldtk(entity: &ldtk::EntityInstance) -> Option<Self> {
+        Some(match entity.identifier.as_str() {
+            "Player" => Self::Player(PlayerIndex::from(entity).0),
+            "Crate" => Self::Crate,
+            "Box" => Self::Box,
+            "DisableBox" => Self::DisableBox,
+            "DevNull" => Self::DevNull,
+            "Goal" => Self::Goal(GoalRequirement::from(entity)),
+            "Target" => Self::Target,
+            "Portal" => Self::Portal(portal_pair(entity)),
+            "Button" => Self::Button(signal_channel(entity)),
+            "Plate" => Self::Plate(signal_channel(entity)),
+            "Door" => Self::Door(signal_channel(entity)),
+            "Conveyor" => Self::Conveyor,
+            name => {
+                if let Some(power) = Power::from_ldtk_name(name) {
+                    Self::Powerup(power)
+                } else {
+                    Self::WallEffect(Power::from_ldtk_name(name.strip_suffix("Wall")?)?)
+                }
+            }
+        })
+    }
+}
+
+/// Portals with the same `Pair` field are linked
+pub fn portal_pair(entity: &ldtk::EntityInstance) -> i32 {
+    match entity
+        .field_instances
+        .iter()
+        .find(|field| field.identifier == "Pair")
+        .map(|field| &field.value)
+    {
+        Some(&FieldValue::Int(Some(pair))) => pair,
+        _ => panic!("Portal pair should be non null int"),
+    }
+}
+
+/// Buttons and plates open the doors with the same `Channel` field
+pub fn signal_channel(entity: &ldtk::EntityInstance) -> i32 {
+    match entity
+        .field_instances
+        .iter()
+        .find(|field| field.identifier == "Channel")
+        .map(|field| &field.value)
+    {
+        Some(&FieldValue::Int(Some(channel))) => channel,
+        _ => panic!("Signal channel should be non null int"),
+    }
+}
+
+impl From<&ldtk::EntityInstance> for GoalRequirement {
+    /// Missing fields keep the [Default], a `null` `Rotation` accepts any rotation
+    fn from(entity: &ldtk::EntityInstance) -> Self {
+        let mut requirement = Self::default();
+        for field in &entity.field_instances {
+            match (field.identifier.as_str(), &field.value) {
+                ("Player", &FieldValue::Int(player)) => requirement.player = player,
+                ("Rotation", &FieldValue::Int(rotation)) => {
+                    requirement.rotation = rotation.map(|rotation| rotation.rem_euclid(4));
+                }
+                _ => {}
+            }
+        }
+        requirement
+    }
+}
+
+impl LevelRules {
+    /// Reads the fields of a level, missing ones keep their default values
+    ///
+    /// Fails on an `Objective` this version does not know.
+    pub fn from_ldtk(level: &ldtk::Level) -> Result<Self, String> {
+        let mut rules = Self::default();
+        for field in &level.field_instances {
+            match (field.identifier.as_str(), &field.value) {
+                ("PushStrength", &FieldValue::Int(strength)) => {
+                    rules.push_strength = strength.map(|strength| strength.max(0) as usize);
+                }
+                ("Objective", FieldValue::Enum(objective)) => {
+                    rules.objective = match objective.as_deref() {
+                        None | Some("AnyPlayer") => Objective::AnyPlayer,
+                        Some("AllPlayers") => Objective::AllPlayers,
+                        Some(other) => {
+                            return Err(format!("Unexpected value for objective: {other:?}"))
+                        }
+                    };
+                }
+                _ => {}
+            }
+        }
+        Ok(rules)
+    }
+}
+
+impl LevelState {
+    /// Builds the initial state of a level without going through the asset server
+    ///
+    /// Fails on IntGrid values that are not in the [CellTable] and on unknown values of level fields.
+    pub fn from_ldtk(level: &ldtk::Level, cells: &CellTable) -> Result<Self, String> {
+        let mut state = Self::default();
+        state.rules =
+            LevelRules::from_ldtk(level).map_err(|e| format!("{}: {e}", level.identifier))?;
+        for layer in level.layer_instances.iter().flatten() {
+            match layer.layer_instance_type {
+                Type::IntGrid => {
+                    for (index, &value) in layer.int_grid_csv.iter().enumerate() {
+                        if value == 0 {
+                            continue;
+                        }
+                        let cell = cells
+                            .get(value)
+                            .map_err(|e| format!("{}: {e}", level.identifier))?;
+                        let coords = int_grid_index_to_grid_coords(
+                            index,
+                            layer.c_wid as u32,
+                            layer.c_hei as u32,
+                        )
+                        .expect("int grid index out of bounds");
+                        Arc::make_mut(&mut state.cells).insert(coords, cell);
+                    }
+                }
+                Type::Entities => {
+                    for entity in &layer.entity_instances {
+                        let Some(kind) = EntityKind::from_ldtk(entity) else {
+                            continue;
+                        };
+                        state.spawn(EntityState {
+                            push_motion: PushMotion::from(entity),
+                            ..EntityState::new(
+                                kind,
+                                ldtk_grid_coords_to_grid_coords(entity.grid, layer.c_hei),
+                                Rotation::from(entity),
+                            )
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+        state.switch_player(0);
+        Ok(state)
+    }
+}
+
+/// Reads a project file directly, without the asset server
+pub fn load_project(path: impl AsRef<Path>) -> Result<ldtk::LdtkJson, String> {
+    let path = path.as_ref();
+    let json = std::fs::read_to_string(path).map_err(|e| format!("{path:?}: {e}"))?;
+    serde_json::from_str(&json).map_err(|e| format!("{path:?}: {e}"))
+}
+
+/// Reads `world.powers.json` and `world.cells.json` from the assets directly, without the asset server
+///
+/// Does nothing for definitions that are already set, see [PowerDef::init].
+pub fn load_defs(assets: impl AsRef<Path>) -> Result<(), String> {
+    fn read<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T, String> {
+        let json = std::fs::read_to_string(path).map_err(|e| format!("{path:?}: {e}"))?;
+        serde_json::from_str(&json).map_err(|e| format!("{path:?}: {e}"))
+    }
+    let assets = assets.as_ref();
+    PowerDef::init(read(&assets.join("world.powers.json"))?);
+    CellDef::init(read(&assets.join("world.cells.json"))?);
+    Ok(())
+}
+
+/// Levels in the same order as [LdtkAsset::iter_levels](bevy_ecs_ldtk::assets::LdtkAsset::iter_levels)
+pub fn iter_levels(project: &ldtk::LdtkJson) -> impl Iterator<Item = &ldtk::Level> {
+    project
+        .levels
+        .iter()
+        .chain(project.worlds.iter().flat_map(|world| world.levels.iter()))
+}
@@ -0,0 +1,238 @@
+//! Turn logic that does not depend on the ECS
+//!
+//! Bevy systems gather the world into a [LevelState], call [step] and then apply the result.
+//! This way levels can be played without an [App](bevy::app::App), e.g. in tests or scripts.
+
+use std::sync::Arc;
+
+use bevy::{math::IVec2, utils::HashMap};
+use bevy_ecs_ldtk::GridCoords;
+use serde::{Deserialize, Serialize};
+
+use super::util::{player_side, side_vec, vec_to_rot};
+
+pub use super::util::{Direction, PushMotion, Rotation};
+
+mod canonical;
+mod cell;
+mod conveyor;
+mod glue;
+mod goal;
+mod grid;
+mod hazard;
+mod ldtk;
+mod player;
+mod power;
+mod resolve;
+mod side;
+mod signals;
+mod solve;
+mod spring;
+mod state;
+#[cfg(test)]
+mod tests;
+
+pub use canonical::*;
+pub use cell::*;
+pub use goal::{GoalRequirement, Objective};
+pub use grid::*;
+pub use ldtk::{iter_levels, load_defs, load_project, portal_pair, signal_channel};
+pub use power::*;
+pub use resolve::*;
+pub use solve::*;
+pub use state::*;
+
+/// What happens during a step
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Input {
+    /// Move the selected player, [Direction::None] is a frame with nothing pressed
+    ///
+    /// A move right after the turn, like when the key is held down through it,
+    /// still has the magnet gravity and slide move of the turn.
+    /// A frame with nothing pressed drops them.
+    Move(Direction),
+    /// Change the selected player, see [LevelState::switch_player]
+    ///
+    /// Only the selection changes, the input of the frame it is pressed on is up to [play_frame].
+    SwitchPlayer(i32),
+    /// Continue the turn: pick up powers, trigger side effects and fall
+    Wait,
+}
+
+impl std::fmt::Display for Input {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Move(Direction::Left) => write!(f, "left"),
+            Self::Move(Direction::None) => write!(f, "none"),
+            Self::Move(Direction::Right) => write!(f, "right"),
+            Self::SwitchPlayer(dir) => write!(f, "switch{dir:+}"),
+            Self::Wait => write!(f, "wait"),
+        }
+    }
+}
+
+/// Parses what [Display](std::fmt::Display) writes
+impl std::str::FromStr for Input {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "left" => Self::Move(Direction::Left),
+            "none" => Self::Move(Direction::None),
+            "right" => Self::Move(Direction::Right),
+            "wait" => Self::Wait,
+            _ => Self::SwitchPlayer(
+                s.strip_prefix("switch")
+                    .and_then(|dir| dir.parse().ok())
+                    .ok_or_else(|| format!("Unexpected input {s:?}"))?,
+            ),
+        })
+    }
+}
+
+/// Description of a visual effect, turned into a [VfxBundle](crate::game::vfx::VfxBundle)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Vfx {
+    pub coords: GridCoords,
+    pub rot: i32,
+    pub atlas: &'static str,
+    pub atlas_size: Option<f32>,
+    pub top: bool,
+    pub mirror: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MoveEvent {
+    pub entity: EntityId,
+    pub kind: MoveKind,
+    pub coords: GridCoords,
+    pub rotation: Rotation,
+    pub sfx: Option<&'static str>,
+    pub end_sfx: Option<&'static str>,
+    pub vfx: Option<Vfx>,
+    pub end_vfx: Option<Vfx>,
+    /// Goes straight to `coords` instead of through the cells in between
+    pub teleport: bool,
+    /// New input direction, when a portal turns the entity
+    pub input: Option<Direction>,
+}
+
+/// Advances the state by one step
+///
+/// Every entity gets at most one [MoveEvent], see [resolve_conflicts] for which one.
+/// Returned state already has all the moves applied.
+pub fn step(state: &LevelState, input: Input) -> (LevelState, Vec<MoveEvent>) {
+    let mut state = state.clone();
+    let mut events = Vec::new();
+    if state.failed.is_some() {
+        return (state, events);
+    }
+    match input {
+        Input::Move(Direction::None) => player::release(&mut state),
+        Input::Move(direction) => {
+            for id in state.ids() {
+                state.get_mut(id).unwrap().turned = false;
+            }
+            player::move_selected(&mut state, direction, &mut events);
+        }
+        Input::SwitchPlayer(dir) => state.switch_player(dir),
+        Input::Wait => {
+            hazard::check(&mut state);
+            if state.failed.is_some() {
+                return (state, events);
+            }
+            side::collect_powerups(&mut state);
+            signals::update(&mut state);
+            let effects = side::detect_side_effects(&state);
+            side::slide_move(&mut state, &effects);
+            side::attach_to_walls(&mut state, &effects);
+            glue::do_glue(&mut state, &effects);
+            player::falling(&state, &mut events);
+            glue::group_falling(&state, &mut events);
+            conveyor::carry(&state, &mut events);
+            side::do_movement(&mut state, &effects, &mut events);
+            side::do_teleport(&mut state, &effects, &mut events);
+            side::do_punch(&state, &effects, &mut events);
+            side::do_rotate(&state, &effects, &mut events);
+            spring::bounce(&state, &mut events);
+            goal::finish_level(&mut state);
+        }
+    }
+
+    let events = resolve_conflicts(&state, events);
+    for event in &events {
+        state.move_entity(event.entity, event.coords, event.rotation);
+        let entity = state.get_mut(event.entity).unwrap();
+        if let Some(input) = event.input {
+            entity.input = input;
+        }
+        if event.teleport {
+            entity.in_portal = true;
+        }
+        if event.kind == MoveKind::Rotate {
+            entity.turned = true;
+        }
+    }
+    if input == Input::Wait {
+        spring::track_falls(&mut state, &events);
+    }
+    (state, events)
+}
+
+/// Steps for a single input to settle, conveyors can keep entities moving forever
+pub const MAX_SETTLE_STEPS: usize = 100;
+
+/// Makes a move and then keeps stepping until nothing moves anymore
+///
+/// Returns the moves of every step, stops after `max_steps` in case entities never settle
+pub fn play(
+    state: &LevelState,
+    input: Input,
+    max_steps: usize,
+) -> (LevelState, Vec<Vec<MoveEvent>>) {
+    let (mut state, events) = step(state, input);
+    let mut history = vec![events];
+    if input == Input::Move(Direction::None) || matches!(input, Input::SwitchPlayer(_)) {
+        return (state, history);
+    }
+    while history.len() <= max_steps {
+        let (new_state, events) = step(&state, Input::Wait);
+        state = new_state;
+        if events.is_empty() {
+            break;
+        }
+        history.push(events);
+    }
+    (state, history)
+}
+
+/// Plays the inputs of a single frame while waiting for input, see [play]
+///
+/// A switch is pressed together with the move before it, like in the game the direction held
+/// only becomes the input of the player selected before, which keeps it until selected again.
+/// The player switched to moves on the same frame, with the input it was left with.
+pub fn play_frame(
+    state: &LevelState,
+    frame: &[Input],
+    max_steps: usize,
+) -> (LevelState, Vec<Vec<MoveEvent>>) {
+    let &[Input::Move(held), Input::SwitchPlayer(dir)] = frame else {
+        let mut state = state.clone();
+        let mut history = Vec::new();
+        for &input in frame {
+            let (new_state, moves) = play(&state, input, max_steps);
+            state = new_state;
+            history.extend(moves);
+        }
+        return (state, history);
+    };
+    let mut state = state.clone();
+    if let Some(entity) = state.selected.and_then(|player| state.get_mut(player)) {
+        entity.input = held;
+    }
+    let (state, _) = step(&state, Input::SwitchPlayer(dir));
+    let input = state
+        .selected
+        .and_then(|player| state.get(player))
+        .map_or(Direction::None, |entity| entity.input);
+    play(&state, Input::Move(input), max_steps)
+}
@@ -0,0 +1,195 @@
+use super::*;
+
+/// Waiting for input with nothing pressed
+///
+/// Magnet gravity and slide moves only last until the first frame without input,
+/// the next turn puts them back from the side effects.
+pub fn release(state: &mut LevelState) {
+    if let Some(entity) = state.selected.and_then(|player| state.get_mut(player)) {
+        entity.input = Direction::None;
+    }
+    for id in state.ids() {
+        let entity = state.get_mut(id).unwrap();
+        entity.override_gravity.clear();
+        entity.slide_move = false;
+    }
+}
+
+/// Moves the selected player, pushing things in front of it
+pub fn move_selected(state: &mut LevelState, direction: Direction, events: &mut Vec<MoveEvent>) {
+    let Some(player) = state.selected else { return };
+    let Some(entity) = state.get_mut(player) else {
+        return;
+    };
+    entity.input = direction;
+    if entity.group.is_some() {
+        glue::move_group(state, player, direction, events);
+        return;
+    }
+    let entity = entity.clone();
+    let coords = entity.coords;
+    let override_gravity = !entity.override_gravity.is_empty();
+
+    let mut moved_to = coords;
+    let mut new_rotation = entity.rotation;
+    let mut ground_rot = 0;
+    let mut slippery = false;
+    for &gravity_dir in entity.gravity() {
+        let move_dir = match direction {
+            Direction::Left => gravity_dir.rotate(IVec2::new(0, -1)),
+            Direction::None => unreachable!(),
+            Direction::Right => gravity_dir.rotate(IVec2::new(0, 1)),
+        };
+        let mut new_coords: GridCoords = (IVec2::from(coords) + move_dir).into();
+        let mut ignore_block = false;
+        if let Some(chain) = push_chain(state, new_coords, move_dir) {
+            ignore_block = true;
+            for pushed in chain {
+                events.push(push_event(state, pushed, move_dir));
+            }
+        }
+        if !ignore_block && state.is_blocked(new_coords, move_dir) {
+            continue;
+        }
+        if override_gravity {
+            let turn_corner_coords = (IVec2::from(new_coords) + gravity_dir).into();
+            if !state.is_blocked(turn_corner_coords, gravity_dir) {
+                new_coords = turn_corner_coords;
+                new_rotation = new_rotation.rotated(direction);
+            }
+        }
+        moved_to = new_coords;
+        ground_rot = vec_to_rot(gravity_dir);
+        slippery = state
+            .cell((IVec2::from(coords) + gravity_dir).into())
+            .slippery;
+        break;
+    }
+    // Sliding players do not roll, the slide itself happens during the next turn
+    if entity.slide_move && moved_to.x != coords.x {
+        return;
+    }
+    events.push(MoveEvent {
+        entity: player,
+        kind: MoveKind::Walk,
+        coords: moved_to,
+        // Slipping over a slippery cell instead of rolling
+        rotation: if slippery {
+            new_rotation
+        } else {
+            new_rotation.rotated(direction)
+        },
+        sfx: Some(if override_gravity {
+            "sfx/magnet.wav"
+        } else {
+            "sfx/move.wav"
+        }),
+        end_sfx: None,
+        vfx: Some(Vfx {
+            coords,
+            rot: ground_rot,
+            atlas: "animation/walk.png",
+            atlas_size: None,
+            top: false,
+            mirror: direction == Direction::Left,
+        }),
+        end_vfx: None,
+        teleport: false,
+        input: None,
+    });
+}
+
+/// Moves a pushed entity by `dir`
+pub(super) fn push_event(state: &LevelState, pushed: EntityId, dir: IVec2) -> MoveEvent {
+    let entity = state.get(pushed).unwrap();
+    MoveEvent {
+        entity: pushed,
+        kind: MoveKind::Push,
+        coords: (IVec2::from(entity.coords) + dir).into(),
+        rotation: pushed_rotation(state, entity, dir),
+        sfx: None,
+        end_sfx: None,
+        vfx: None,
+        end_vfx: None,
+        teleport: false,
+        input: None,
+    }
+}
+
+/// Rotation of a pushed entity after moving by `dir`
+///
+/// Rolling entities roll over the edge they stand on, relative to their own gravity,
+/// so pushing along the gravity, onto a slide power or over a slippery cell does not rotate them.
+fn pushed_rotation(state: &LevelState, entity: &EntityState, dir: IVec2) -> Rotation {
+    let gravity_dir = entity.gravity()[0];
+    let slippery = state
+        .cell((IVec2::from(entity.coords) + gravity_dir).into())
+        .slippery;
+    if entity.push_motion != PushMotion::Roll || entity.slide_move || slippery {
+        return entity.rotation;
+    }
+    let roll = if dir == gravity_dir.rotate(IVec2::new(0, -1)) {
+        Direction::Left
+    } else if dir == gravity_dir.rotate(IVec2::new(0, 1)) {
+        Direction::Right
+    } else {
+        Direction::None
+    };
+    entity.rotation.rotated(roll)
+}
+
+/// Pushable entities in a row starting at `coords`, if all of them can be moved by `dir`
+///
+/// The row can not be longer than [LevelRules::push_strength],
+/// glued entities can not be pushed apart from their group.
+pub(super) fn push_chain(
+    state: &LevelState,
+    coords: GridCoords,
+    dir: IVec2,
+) -> Option<Vec<EntityId>> {
+    let mut chain = Vec::new();
+    let mut coords = coords;
+    while let Some(pushed) = state.find_at(coords, |entity| entity.kind.pushable()) {
+        if state.get(pushed).unwrap().group.is_some() {
+            return None;
+        }
+        chain.push(pushed);
+        coords = (IVec2::from(coords) + dir).into();
+    }
+    if chain.is_empty()
+        || state.is_blocked(coords, dir)
+        || state
+            .rules
+            .push_strength
+            .is_some_and(|strength| chain.len() > strength)
+    {
+        return None;
+    }
+    Some(chain)
+}
+
+/// Glued entities fall together, see [glue::group_falling]
+pub fn falling(state: &LevelState, events: &mut Vec<MoveEvent>) {
+    for (id, entity) in state.iter() {
+        if !entity.kind.movable() || entity.group.is_some() {
+            continue;
+        }
+        for &gravity in entity.gravity() {
+            let new_coords = (IVec2::from(entity.coords) + gravity).into();
+            if !state.is_blocked(new_coords, gravity) {
+                events.push(MoveEvent {
+                    entity: id,
+                    kind: MoveKind::Fall,
+                    coords: new_coords,
+                    rotation: entity.rotation,
+                    sfx: None,
+                    end_sfx: None,
+                    vfx: None,
+                    end_vfx: None,
+                    teleport: false,
+                    input: None,
+                });
+            }
+        }
+    }
+}
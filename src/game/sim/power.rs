@@ -0,0 +1,224 @@
+use std::sync::OnceLock;
+
+use super::*;
+
+/// A power that can be attached to a side, index into [PowerDef::all]
+///
+/// Serialized by its [PowerDef::name], so saved states survive reordering the definitions.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Power(pub u8);
+
+impl std::fmt::Debug for Power {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.def().name)
+    }
+}
+
+impl Power {
+    pub fn all() -> impl Iterator<Item = Self> {
+        (0..PowerDef::all().len()).map(|index| Self(index as u8))
+    }
+
+    pub fn def(self) -> &'static PowerDef {
+        &PowerDef::all()[self.0 as usize]
+    }
+
+    /// Name of the powerup entity in ldtk, wall version has `Wall` appended
+    pub fn ldtk_name(self) -> &'static str {
+        &self.def().ldtk_name
+    }
+
+    pub fn from_ldtk_name(name: &str) -> Option<Self> {
+        Self::all().find(|power| power.ldtk_name() == name)
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::all().find(|power| power.def().name == name)
+    }
+}
+
+impl Serialize for Power {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.def().name)
+    }
+}
+
+impl<'de> Deserialize<'de> for Power {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Self::from_name(&name)
+            .ok_or_else(|| serde::de::Error::custom(format!("No power named {name:?}")))
+    }
+}
+
+/// Which sides of an entity a power works on, relative to gravity
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(default)]
+pub struct ActiveSides {
+    pub below: bool,
+    pub side: bool,
+    pub above: bool,
+}
+
+/// What has to be next to a side for its power to activate
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub enum Requirement {
+    /// A trigger cell or entity, see [LevelState::is_trigger]
+    #[default]
+    Trigger,
+    /// Anything blocking, see [LevelState::is_blocked]
+    Blocked,
+    /// Active no matter what is there
+    Nothing,
+}
+
+/// A step of a [MovementDef] path, relative to the direction away from the side
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(default)]
+pub struct PathStep {
+    /// Cells away from the side
+    pub along: i32,
+    /// Cells sideways in the input direction
+    pub steer: i32,
+    /// Only taken when moving straight up
+    pub only_up: bool,
+}
+
+/// Description of a [Vfx], played at the side
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct VfxDef {
+    pub atlas: String,
+    pub atlas_size: Option<f32>,
+    pub top: bool,
+    pub mirror: bool,
+}
+
+impl VfxDef {
+    pub fn at(&'static self, coords: GridCoords, rot: i32) -> Vfx {
+        Vfx {
+            coords,
+            rot,
+            atlas: &self.atlas,
+            atlas_size: self.atlas_size,
+            top: self.top,
+            mirror: self.mirror,
+        }
+    }
+}
+
+/// How an activated power moves the entity
+#[derive(Debug, Clone, Deserialize)]
+pub struct MovementDef {
+    pub priority: MoveKind,
+    /// The entity goes along the path until something blocks it
+    pub path: Vec<PathStep>,
+    /// Being blocked before this many steps counts as hitting a wall
+    #[serde(default)]
+    pub hit_wall_within: usize,
+    /// Roll in the input direction when moving straight up
+    #[serde(default)]
+    pub roll_up: bool,
+    /// Only moving straight up while standing on something, the ground holds the entity
+    #[serde(default)]
+    pub friction: bool,
+    /// Roll in the input direction when there is nothing at the side after moving
+    #[serde(default)]
+    pub roll_off: bool,
+    pub sfx: Option<String>,
+    pub roll_off_sfx: Option<String>,
+    pub hit_wall_sfx: Option<String>,
+    pub vfx: Option<VfxDef>,
+    pub hit_wall_vfx: Option<VfxDef>,
+}
+
+/// Teleporting through a [EntityKind::Portal] the side touches
+#[derive(Debug, Clone, Deserialize)]
+pub struct PortalDef {
+    pub priority: MoveKind,
+    pub sfx: Option<String>,
+    /// Played at the entry portal
+    pub vfx: Option<VfxDef>,
+    /// Played at the exit portal
+    pub end_vfx: Option<VfxDef>,
+}
+
+/// Knocking away the movable entity the side touches
+#[derive(Debug, Clone, Deserialize)]
+pub struct PunchDef {
+    pub priority: MoveKind,
+    /// The entity flies up to this many cells, until something blocks it
+    pub distance: i32,
+    pub sfx: Option<String>,
+    /// Played at the punched entity
+    pub vfx: Option<VfxDef>,
+}
+
+/// Turning the entity the side touches in place, once per turn
+#[derive(Debug, Clone, Deserialize)]
+pub struct RotateDef {
+    /// Quarter turns, positive is counterclockwise
+    pub turn: i32,
+    pub sfx: Option<String>,
+    /// Played at the turned entity
+    pub vfx: Option<VfxDef>,
+}
+
+/// Everything about a power, loaded from `assets/world.powers.json`
+#[derive(Debug, Clone, Deserialize)]
+pub struct PowerDef {
+    pub name: String,
+    pub ldtk_name: String,
+    pub active: ActiveSides,
+    #[serde(default)]
+    pub requires: Requirement,
+    /// A movable entity touched by the side gets the effect too, on its side facing back
+    #[serde(default = "shared_by_default")]
+    pub shared: bool,
+    /// Moving sideways slides instead of rolling, see [EntityState::slide_move]
+    #[serde(default)]
+    pub slide_move: bool,
+    /// Moving keeps the entity [EntityState::sliding] while it stays on a surface
+    #[serde(default)]
+    pub sliding: bool,
+    /// Gravity pulls the entity towards the side
+    #[serde(default)]
+    pub attach: bool,
+    /// The side sticks to the movable entity it touches, see [EntityState::group]
+    #[serde(default)]
+    pub glue: bool,
+    pub movement: Option<MovementDef>,
+    pub portal: Option<PortalDef>,
+    pub punch: Option<PunchDef>,
+    pub rotate: Option<RotateDef>,
+}
+
+fn shared_by_default() -> bool {
+    true
+}
+
+static DEFS: OnceLock<Vec<PowerDef>> = OnceLock::new();
+
+impl PowerDef {
+    /// Definitions set by [PowerDef::init], panics before that
+    pub fn all() -> &'static [PowerDef] {
+        DEFS.get().expect("world.powers.json is not loaded")
+    }
+
+    /// Sets the definitions once, returns `false` if they were already set
+    ///
+    /// [Power]s are indices into them, so they can not change while the game runs.
+    pub fn init(defs: Vec<PowerDef>) -> bool {
+        DEFS.set(defs).is_ok()
+    }
+
+    /// Whether the power works on a side facing `direction`
+    pub fn active_towards(&self, direction: IVec2) -> bool {
+        match direction {
+            IVec2 { y: -1, .. } => self.active.below,
+            IVec2 { y: 1, .. } => self.active.above,
+            IVec2 { y: 0, .. } => self.active.side,
+            _ => unreachable!(),
+        }
+    }
+}
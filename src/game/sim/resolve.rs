@@ -0,0 +1,112 @@
+use std::cmp::Reverse;
+
+use super::*;
+
+/// Why an entity moves, later variants win conflicts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+pub enum MoveKind {
+    /// Turning in place, anything else the entity does wins over it
+    Rotate,
+    Fall,
+    /// Carried by a conveyor, powers win over it
+    Carry,
+    Slide,
+    Jump,
+    Bounce,
+    Teleport,
+    Push,
+    Walk,
+}
+
+/// Leaves at most one move per entity and makes sure blocking entities never share a cell
+///
+/// Priority rules:
+/// - an entity keeps its highest [MoveKind], the last one of them if there are several
+/// - an entity that stays in place keeps its cell, moves into it are rejected
+/// - when several entities move into the same cell the highest [MoveKind] wins,
+///   on a tie the entity coming from the lowest cell does, then the one from the left
+/// - two entities can not swap places, both moves are rejected
+///
+/// Entities whose move got rejected stay in place, which can reject moves into their cells in turn.
+/// Rejecting a move of a glued entity rejects the moves of its whole group.
+/// Order of the moves that are left is kept.
+pub fn resolve_conflicts(state: &LevelState, events: Vec<MoveEvent>) -> Vec<MoveEvent> {
+    // Index of the accepted move of every moving entity
+    let mut moves: HashMap<EntityId, usize> = HashMap::default();
+    for (index, event) in events.iter().enumerate() {
+        if moves
+            .get(&event.entity)
+            .is_none_or(|&other| events[other].kind <= event.kind)
+        {
+            moves.insert(event.entity, index);
+        }
+    }
+
+    loop {
+        let mut rejected = Vec::new();
+
+        // Who ends up where, `None` meaning the entity stays in place
+        let mut claims: HashMap<GridCoords, Vec<(EntityId, Option<MoveKind>)>> = HashMap::default();
+        for (id, entity) in state.iter() {
+            if !entity.kind.blocking() {
+                continue;
+            }
+            let claim = match moves.get(&id) {
+                Some(&index) if events[index].coords != entity.coords => {
+                    (events[index].coords, Some(events[index].kind))
+                }
+                _ => (entity.coords, None),
+            };
+            claims.entry(claim.0).or_default().push((id, claim.1));
+        }
+        for claimants in claims.values().filter(|claimants| claimants.len() > 1) {
+            let &(winner, _) = claimants
+                .iter()
+                .max_by_key(|&&(id, kind)| {
+                    let from = state.get(id).unwrap().coords;
+                    (kind.is_none(), kind, Reverse((from.y, from.x)))
+                })
+                .unwrap();
+            rejected.extend(
+                claimants
+                    .iter()
+                    .filter(|&&(id, kind)| id != winner && kind.is_some())
+                    .map(|&(id, _)| id),
+            );
+        }
+
+        for (&id, &index) in &moves {
+            let Some(entity) = state.get(id) else {
+                continue;
+            };
+            if !entity.kind.blocking() {
+                continue;
+            }
+            let swapped = state
+                .find_at(events[index].coords, |other| other.kind.blocking())
+                .and_then(|other| Some(events[*moves.get(&other)?].coords))
+                .is_some_and(|coords| coords == entity.coords && coords != events[index].coords);
+            if swapped {
+                rejected.push(id);
+            }
+        }
+
+        if rejected.is_empty() {
+            break;
+        }
+        // Groups only move as a whole
+        for id in rejected.clone() {
+            rejected.extend(state.group_of(id));
+        }
+        for id in rejected {
+            moves.remove(&id);
+        }
+    }
+
+    events
+        .into_iter()
+        .enumerate()
+        .filter(|(index, event)| moves.get(&event.entity) == Some(index))
+        .map(|(_, event)| event)
+        .collect()
+}
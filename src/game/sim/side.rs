@@ -0,0 +1,416 @@
+use super::*;
+
+/// A power got activated on a side of an entity
+#[derive(Debug, Clone, Copy)]
+pub struct SideEffectEvent {
+    pub entity: EntityId,
+    pub side: i32,
+    pub power: Power,
+}
+
+/// DevNulls delete the power on the bottom side, then powerups are put on blank sides
+pub fn collect_powerups(state: &mut LevelState) {
+    for id in state.ids() {
+        let Some(entity) = state.get(id) else {
+            continue;
+        };
+        if !entity.kind.pickup() {
+            continue;
+        }
+        let coords = entity.coords;
+        let bottom = entity.rotation.normalized().0 as usize;
+        if entity.sides[bottom].is_some() {
+            if let Some(devnull) = state.find_at(coords, |other| other.kind == EntityKind::DevNull)
+            {
+                state.despawn(devnull);
+                state.get_mut(id).unwrap().sides[bottom] = None;
+            }
+        }
+        let powerups: Vec<EntityId> = state
+            .at(coords)
+            .filter(|(_, other)| matches!(other.kind, EntityKind::Powerup(_)))
+            .map(|(id, _)| id)
+            .collect();
+        for powerup in powerups {
+            let powerup_state = state.get(powerup).unwrap();
+            let EntityKind::Powerup(power) = powerup_state.kind else {
+                unreachable!()
+            };
+            let powerup_rotation = powerup_state.rotation.0;
+            let entity = state.get_mut(id).unwrap();
+            let side = (entity.rotation.0 - powerup_rotation).rem_euclid(4) as usize;
+            if entity.sides[side].is_none() {
+                entity.sides[side] = Some(power);
+                state.despawn(powerup);
+            }
+        }
+    }
+}
+
+pub fn detect_side_effects(state: &LevelState) -> Vec<SideEffectEvent> {
+    let mut events = Vec::new();
+    for power in Power::all() {
+        detect_wall_side_effect(state, power, &mut events);
+        detect_side_effect(state, power, &mut events);
+    }
+    events
+}
+
+fn detect_wall_side_effect(state: &LevelState, power: Power, events: &mut Vec<SideEffectEvent>) {
+    for (id, entity) in state.iter() {
+        if !entity.kind.movable() || !entity.kind.trigger() {
+            continue;
+        }
+        for (_, effect) in state.at(entity.coords) {
+            if effect.kind != EntityKind::WallEffect(power) {
+                continue;
+            }
+            events.push(SideEffectEvent {
+                entity: id,
+                side: player_side(&entity.rotation, -side_vec(effect.rotation.0, 0)),
+                power,
+            });
+        }
+    }
+}
+
+fn detect_side_effect(state: &LevelState, power: Power, events: &mut Vec<SideEffectEvent>) {
+    for (id, entity) in state.iter() {
+        if !entity.kind.movable() {
+            continue;
+        }
+        let mut sides: Vec<i32> = (0..4)
+            .filter(|&side| entity.sides[side as usize] == Some(power))
+            .collect();
+        sides.sort_by_key(|&side| -side_vec(entity.rotation.0, side).y);
+        for side in sides {
+            let direction = side_vec(entity.rotation.0, side);
+            if !power.def().active_towards(direction) {
+                continue;
+            }
+            let side_coords = (IVec2::from(entity.coords) + direction).into();
+            let activated = match power.def().requires {
+                Requirement::Trigger => state.is_trigger(side_coords),
+                Requirement::Blocked => state.is_blocked(side_coords, direction),
+                Requirement::Nothing => true,
+            };
+            if activated {
+                events.push(SideEffectEvent {
+                    entity: id,
+                    side,
+                    power,
+                });
+                let other = state
+                    .find_at(side_coords, |other| other.kind.movable())
+                    .filter(|_| power.def().shared);
+                if let Some(other) = other {
+                    events.push(SideEffectEvent {
+                        entity: other,
+                        side: player_side(&state.get(other).unwrap().rotation, direction),
+                        power,
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Entities on a [PowerDef::slide_move] power move sideways without rolling
+pub fn slide_move(state: &mut LevelState, effects: &[SideEffectEvent]) {
+    for id in state.ids() {
+        let slide_move = effects
+            .iter()
+            .any(|effect| effect.entity == id && effect.power.def().slide_move);
+        state.get_mut(id).unwrap().slide_move = slide_move;
+    }
+}
+
+/// [PowerDef::attach] powers and [Cell::sticky] cells change gravity towards the walls
+pub fn attach_to_walls(state: &mut LevelState, effects: &[SideEffectEvent]) {
+    for id in state.ids() {
+        let coords = state.get(id).unwrap().coords;
+        let sticky: Vec<IVec2> = (0..4)
+            .map(|side| side_vec(0, side))
+            .filter(|&dir| state.cell((IVec2::from(coords) + dir).into()).sticky)
+            .collect();
+        let entity = state.get_mut(id).unwrap();
+        entity.override_gravity = effects
+            .iter()
+            .filter(|effect| effect.entity == id && effect.power.def().attach)
+            .map(|effect| side_vec(entity.rotation.0, effect.side))
+            .collect();
+        if entity.kind.movable() {
+            for dir in sticky {
+                if !entity.override_gravity.contains(&dir) {
+                    entity.override_gravity.push(dir);
+                }
+            }
+        }
+    }
+}
+
+/// Moves entities along the paths of their [PowerDef::movement]s
+pub fn do_movement(
+    state: &mut LevelState,
+    effects: &[SideEffectEvent],
+    events: &mut Vec<MoveEvent>,
+) {
+    // Whether entities with a sliding power are still sliding, the last effect decides
+    let mut sliding: HashMap<EntityId, bool> = HashMap::default();
+    for effect in effects {
+        let def = effect.power.def();
+        let Some(movement) = &def.movement else {
+            continue;
+        };
+        let Some(entity) = state.get(effect.entity) else {
+            continue;
+        };
+        if entity.group.is_some() {
+            continue;
+        }
+        let side_dir = side_vec(entity.rotation.0, effect.side);
+        let move_dir = -side_dir;
+        let up = move_dir == IVec2::new(0, 1);
+        let below = (IVec2::from(entity.coords) + IVec2::NEG_Y).into();
+        if movement.friction && !up && state.is_blocked(below, IVec2::NEG_Y) {
+            continue;
+        }
+
+        let mut path: Vec<GridCoords> = movement
+            .path
+            .iter()
+            .filter(|step| up || !step.only_up)
+            .map(|step| {
+                let offset = IVec2::new(step.along, -step.steer * entity.input.delta());
+                (IVec2::from(entity.coords) + move_dir.rotate(offset)).into()
+            })
+            .collect();
+        let mut hit_wall = false;
+        let mut from = entity.coords;
+        let blocked_at = path.iter().position(|&coords| {
+            let dir = IVec2::from(coords) - IVec2::from(from);
+            from = coords;
+            state.is_blocked(coords, dir)
+        });
+        if let Some(index) = blocked_at {
+            path.truncate(index);
+            hit_wall = index < movement.hit_wall_within;
+        }
+        let Some(&last) = path.last() else {
+            if def.sliding {
+                sliding.insert(effect.entity, false);
+            }
+            continue;
+        };
+
+        let supported = state.is_blocked((IVec2::from(last) + side_dir).into(), side_dir);
+        let rolled_off = movement.roll_off && !supported;
+        if def.sliding {
+            sliding.insert(effect.entity, supported);
+        }
+        events.push(MoveEvent {
+            entity: effect.entity,
+            kind: movement.priority,
+            coords: last,
+            rotation: if (movement.roll_up && up) || rolled_off {
+                entity.rotation.rotated(entity.input)
+            } else {
+                entity.rotation
+            },
+            sfx: if rolled_off {
+                movement.roll_off_sfx.as_deref()
+            } else {
+                movement.sfx.as_deref()
+            },
+            end_sfx: movement.hit_wall_sfx.as_deref().filter(|_| hit_wall),
+            vfx: movement
+                .vfx
+                .as_ref()
+                .map(|vfx| vfx.at(entity.coords, vec_to_rot(side_dir))),
+            end_vfx: movement
+                .hit_wall_vfx
+                .as_ref()
+                .filter(|_| hit_wall)
+                .map(|vfx| vfx.at(last, vec_to_rot(side_dir))),
+            teleport: false,
+            input: None,
+        });
+    }
+    for id in state.ids() {
+        let entity = state.get_mut(id).unwrap();
+        entity.sliding = sliding.get(&id).copied().unwrap_or(false);
+    }
+}
+
+/// Sends entities through the [EntityKind::Portal]s their [PowerDef::portal] sides touch
+///
+/// The entity is turned so that what went into the entry wall comes out of the exit wall.
+/// It keeps its momentum: the distance it fell and walked into the entry wall
+/// launches it out of the exit portal, cut short by whatever blocks it.
+/// Its input is turned along, an input that would point up or down is dropped.
+pub fn do_teleport(
+    state: &mut LevelState,
+    effects: &[SideEffectEvent],
+    events: &mut Vec<MoveEvent>,
+) {
+    for id in state.ids() {
+        let coords = state.get(id).unwrap().coords;
+        if state
+            .find_at(coords, |other| matches!(other.kind, EntityKind::Portal(_)))
+            .is_none()
+        {
+            state.get_mut(id).unwrap().in_portal = false;
+        }
+    }
+    for effect in effects {
+        let Some(portal) = &effect.power.def().portal else {
+            continue;
+        };
+        let Some(entity) = state.get(effect.entity) else {
+            continue;
+        };
+        if entity.in_portal || entity.group.is_some() {
+            continue;
+        }
+        let side_dir = side_vec(entity.rotation.0, effect.side);
+        let entry = state
+            .at(entity.coords)
+            .find_map(|(id, other)| match other.kind {
+                EntityKind::Portal(pair) if side_vec(other.rotation.0, 0) == side_dir => {
+                    Some((id, pair))
+                }
+                _ => None,
+            });
+        let Some((entry, pair)) = entry else { continue };
+        let Some((_, exit)) = state
+            .iter()
+            .find(|&(id, other)| id != entry && other.kind == EntityKind::Portal(pair))
+        else {
+            continue;
+        };
+        // Coming out of the portal, away from its wall
+        let out = -side_vec(exit.rotation.0, 0);
+        if state.is_blocked(exit.coords, out) {
+            continue;
+        }
+        let turn = (vec_to_rot(out) - vec_to_rot(side_dir)).rem_euclid(4);
+        let turned = |v: IVec2| match v {
+            IVec2::ZERO => v,
+            _ => side_vec(vec_to_rot(v) + turn, 0) * v.abs().max_element(),
+        };
+        let fall = turned(IVec2::NEG_Y * entity.fall_distance);
+        let walk = turned(IVec2::X * entity.input.delta());
+        let mut coords = exit.coords;
+        for distance in 1..=fall.dot(out).max(0) + walk.dot(out).max(0) {
+            let next = (IVec2::from(exit.coords) + out * distance).into();
+            if state.is_blocked(next, out) {
+                break;
+            }
+            coords = next;
+        }
+        let input = match walk.x {
+            1 => Direction::Right,
+            -1 => Direction::Left,
+            _ => Direction::None,
+        };
+        events.push(MoveEvent {
+            entity: effect.entity,
+            kind: portal.priority,
+            coords,
+            rotation: Rotation(entity.rotation.0 + turn),
+            sfx: portal.sfx.as_deref(),
+            end_sfx: None,
+            vfx: portal
+                .vfx
+                .as_ref()
+                .map(|vfx| vfx.at(entity.coords, vec_to_rot(side_dir))),
+            end_vfx: portal
+                .end_vfx
+                .as_ref()
+                .map(|vfx| vfx.at(exit.coords, vec_to_rot(-out))),
+            teleport: true,
+            input: (input != entity.input).then_some(input),
+        });
+    }
+}
+
+/// Knocks away movable entities touched by [PowerDef::punch] sides
+///
+/// The punched entity keeps its rotation and stops before anything blocking, falling afterwards.
+pub fn do_punch(state: &LevelState, effects: &[SideEffectEvent], events: &mut Vec<MoveEvent>) {
+    for effect in effects {
+        let Some(punch) = &effect.power.def().punch else {
+            continue;
+        };
+        let Some(entity) = state.get(effect.entity) else {
+            continue;
+        };
+        let dir = side_vec(entity.rotation.0, effect.side);
+        let target_coords = (IVec2::from(entity.coords) + dir).into();
+        let target = state.find_at(target_coords, |other| {
+            other.kind.movable() && other.group.is_none()
+        });
+        let Some(target) = target else {
+            continue;
+        };
+        let target_state = state.get(target).unwrap();
+        let mut last = None;
+        for distance in 1..=punch.distance {
+            let coords = (IVec2::from(target_coords) + dir * distance).into();
+            if state.is_blocked(coords, dir) {
+                break;
+            }
+            last = Some(coords);
+        }
+        let Some(last) = last else { continue };
+        events.push(MoveEvent {
+            entity: target,
+            kind: punch.priority,
+            coords: last,
+            rotation: target_state.rotation,
+            sfx: punch.sfx.as_deref(),
+            end_sfx: None,
+            vfx: punch
+                .vfx
+                .as_ref()
+                .map(|vfx| vfx.at(target_coords, vec_to_rot(dir))),
+            end_vfx: None,
+            teleport: false,
+            input: None,
+        });
+    }
+}
+
+/// Turns entities with sides that are touched by [PowerDef::rotate] sides
+///
+/// The entity stays in its cell and its powers turn with it.
+pub fn do_rotate(state: &LevelState, effects: &[SideEffectEvent], events: &mut Vec<MoveEvent>) {
+    for effect in effects {
+        let Some(rotate) = &effect.power.def().rotate else {
+            continue;
+        };
+        let Some(entity) = state.get(effect.entity) else {
+            continue;
+        };
+        let dir = side_vec(entity.rotation.0, effect.side);
+        let target_coords = (IVec2::from(entity.coords) + dir).into();
+        let target = state.find_at(target_coords, |other| other.kind.pickup() && !other.turned);
+        let Some(target) = target else { continue };
+        let target_state = state.get(target).unwrap();
+        events.push(MoveEvent {
+            entity: target,
+            kind: MoveKind::Rotate,
+            coords: target_coords,
+            rotation: Rotation(target_state.rotation.0 + rotate.turn),
+            sfx: rotate.sfx.as_deref(),
+            end_sfx: None,
+            vfx: rotate
+                .vfx
+                .as_ref()
+                .map(|vfx| vfx.at(target_coords, vec_to_rot(dir))),
+            end_vfx: None,
+            teleport: false,
+            input: None,
+        });
+    }
+}
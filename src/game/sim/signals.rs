@@ -0,0 +1,39 @@
+use super::*;
+
+/// Presses buttons and plates, then opens and closes the doors on their channels
+///
+/// Runs before anything moves, so entities fall through a door in the same step it opens.
+/// Doors do not close on an entity standing in them.
+pub fn update(state: &mut LevelState) {
+    for id in state.ids() {
+        let entity = state.get(id).unwrap();
+        let pressed = state
+            .find_at(entity.coords, |other| other.kind.movable())
+            .is_some();
+        let active = match entity.kind {
+            EntityKind::Button(_) => entity.active || pressed,
+            EntityKind::Plate(_) => pressed,
+            _ => continue,
+        };
+        state.set_active(id, active);
+    }
+
+    let channels: Vec<i32> = state
+        .iter()
+        .filter(|(_, entity)| entity.active)
+        .filter_map(|(_, entity)| match entity.kind {
+            EntityKind::Button(channel) | EntityKind::Plate(channel) => Some(channel),
+            _ => None,
+        })
+        .collect();
+    for id in state.ids() {
+        let entity = state.get(id).unwrap();
+        let EntityKind::Door(channel) = entity.kind else {
+            continue;
+        };
+        let occupied = state
+            .find_at(entity.coords, |other| other.kind.movable())
+            .is_some();
+        state.set_active(id, channels.contains(&channel) || occupied);
+    }
+}
@@ -0,0 +1,146 @@
+use std::{
+    collections::{hash_map::DefaultHasher, VecDeque},
+    hash::{Hash, Hasher},
+};
+
+use bevy::utils::HashSet;
+
+use super::*;
+
+/// Everything the player can do on a frame while waiting for input
+///
+/// Moves right after another input are made as if the key was held down through the turn,
+/// to move without the magnets and slide powers of the last turn the player waits first.
+/// A switch is pressed together with a direction or nothing, see [play_frame].
+pub const FRAMES: [&[Input]; 9] = [
+    &[Input::Move(Direction::Left)],
+    &[Input::Move(Direction::Right)],
+    &[Input::Move(Direction::None)],
+    &[Input::Move(Direction::None), Input::SwitchPlayer(1)],
+    &[Input::Move(Direction::None), Input::SwitchPlayer(-1)],
+    &[Input::Move(Direction::Left), Input::SwitchPlayer(1)],
+    &[Input::Move(Direction::Left), Input::SwitchPlayer(-1)],
+    &[Input::Move(Direction::Right), Input::SwitchPlayer(1)],
+    &[Input::Move(Direction::Right), Input::SwitchPlayer(-1)],
+];
+
+#[derive(Debug, Clone)]
+pub struct SearchResult {
+    /// Shortest inputs that complete the level
+    pub solution: Option<Vec<Input>>,
+    /// Number of distinct states that were visited
+    pub explored: usize,
+    /// Every reachable state was visited, so no solution means the level is unsolvable
+    pub exhausted: bool,
+}
+
+/// Area of the level, whatever leaves it is never coming back
+struct Bounds {
+    min: IVec2,
+    max: IVec2,
+}
+
+impl Bounds {
+    fn new(state: &LevelState) -> Self {
+        let coords: Vec<IVec2> = state
+            .cells
+            .keys()
+            .chain(state.iter().map(|(_, entity)| &entity.coords))
+            .map(|&coords| IVec2::from(coords))
+            .collect();
+        Self {
+            min: coords
+                .iter()
+                .copied()
+                .fold(IVec2::splat(i32::MAX), IVec2::min),
+            max: coords
+                .iter()
+                .copied()
+                .fold(IVec2::splat(i32::MIN), IVec2::max),
+        }
+    }
+
+    fn contains(&self, state: &LevelState) -> bool {
+        state.iter().all(|(_, entity)| {
+            let coords = IVec2::from(entity.coords);
+            coords.cmpge(self.min).all() && coords.cmple(self.max).all()
+        })
+    }
+}
+
+/// 128 bit hash of the [CanonicalState], visited states are only kept as these to save memory
+///
+/// Two states sharing one is too unlikely to matter even for millions of states.
+fn fingerprint(state: &LevelState) -> u128 {
+    let canonical = state.canonical();
+    let hash = |salt: u8| {
+        let mut hasher = DefaultHasher::new();
+        salt.hash(&mut hasher);
+        canonical.hash(&mut hasher);
+        hasher.finish()
+    };
+    (hash(0) as u128) << 64 | hash(1) as u128
+}
+
+/// Breadth first search for the shortest sequence of [FRAMES] that completes the level
+///
+/// `level` is the state as loaded, before anything fell into place.
+/// Gives up after visiting `max_states` states.
+/// Inputs that do not settle within [MAX_SETTLE_STEPS] are dead ends.
+pub fn solve(level: &LevelState, max_states: usize) -> SearchResult {
+    let bounds = Bounds::new(level);
+    let (start, _) = play(level, Input::Wait, MAX_SETTLE_STEPS);
+    if start.completed {
+        return SearchResult {
+            solution: Some(Vec::new()),
+            explored: 1,
+            exhausted: false,
+        };
+    }
+
+    // How every visited state was reached, as the index of the previous one and the frame
+    let mut parents: Vec<Option<(usize, &[Input])>> = vec![None];
+    let mut visited = HashSet::default();
+    visited.insert(fingerprint(&start));
+    let mut queue = VecDeque::from([(start, 0)]);
+    while let Some((state, index)) = queue.pop_front() {
+        for frame in FRAMES {
+            let (next, history) = play_frame(&state, frame, MAX_SETTLE_STEPS);
+            if history.len() > MAX_SETTLE_STEPS || !bounds.contains(&next) || next.failed.is_some()
+            {
+                continue;
+            }
+            if !visited.insert(fingerprint(&next)) {
+                continue;
+            }
+            parents.push(Some((index, frame)));
+            if next.completed {
+                let mut solution = Vec::new();
+                let mut current = parents.len() - 1;
+                while let Some((parent, frame)) = parents[current] {
+                    solution.extend(frame.iter().rev());
+                    current = parent;
+                }
+                solution.reverse();
+                return SearchResult {
+                    solution: Some(solution),
+                    explored: visited.len(),
+                    exhausted: false,
+                };
+            }
+            if visited.len() >= max_states {
+                return SearchResult {
+                    solution: None,
+                    explored: visited.len(),
+                    exhausted: false,
+                };
+            }
+            queue.push_back((next, parents.len() - 1));
+        }
+    }
+    SearchResult {
+        solution: None,
+        explored: visited.len(),
+        exhausted: true,
+    }
+}
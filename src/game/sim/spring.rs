@@ -0,0 +1,82 @@
+use super::*;
+
+/// Entities landing on a [Cell::spring] are launched back by the distance they fell
+///
+/// The path is cut short by whatever blocks it, like a jump.
+pub fn bounce(state: &LevelState, events: &mut Vec<MoveEvent>) {
+    for (id, entity) in state.iter() {
+        if !entity.kind.movable() || entity.fall_distance == 0 || entity.group.is_some() {
+            continue;
+        }
+        let gravity = entity.gravity();
+        let falling = gravity.iter().any(|&gravity| {
+            !state.is_blocked((IVec2::from(entity.coords) + gravity).into(), gravity)
+        });
+        if falling {
+            continue;
+        }
+        let Some(&gravity) = gravity.iter().find(|&&gravity| {
+            let below = (IVec2::from(entity.coords) + gravity).into();
+            state.cell(below).spring
+        }) else {
+            continue;
+        };
+        let mut last = None;
+        for distance in 1..=entity.fall_distance {
+            let coords = (IVec2::from(entity.coords) - gravity * distance).into();
+            if state.is_blocked(coords, -gravity) {
+                break;
+            }
+            last = Some(coords);
+        }
+        let Some(last) = last else { continue };
+        events.push(MoveEvent {
+            entity: id,
+            kind: MoveKind::Bounce,
+            coords: last,
+            rotation: entity.rotation,
+            sfx: Some("sfx/jump.wav"),
+            end_sfx: None,
+            vfx: Some(Vfx {
+                coords: entity.coords,
+                rot: vec_to_rot(gravity),
+                atlas: "animation/jump.png",
+                atlas_size: None,
+                top: true,
+                mirror: false,
+            }),
+            end_vfx: None,
+            teleport: false,
+            input: None,
+        });
+    }
+}
+
+/// Counts how far entities have fallen after the moves of a step are applied
+///
+/// Any other move or landing resets the distance, falling back after a bounce is not counted
+/// so that entities do not bounce forever.
+pub fn track_falls(state: &mut LevelState, events: &[MoveEvent]) {
+    for id in state.ids() {
+        let kind = events
+            .iter()
+            .find(|event| event.entity == id)
+            .map(|event| event.kind);
+        let entity = state.get_mut(id).unwrap();
+        match kind {
+            Some(MoveKind::Fall) => {
+                if !entity.bouncing {
+                    entity.fall_distance += 1;
+                }
+            }
+            Some(kind) => {
+                entity.fall_distance = 0;
+                entity.bouncing = kind == MoveKind::Bounce;
+            }
+            None => {
+                entity.fall_distance = 0;
+                entity.bouncing = false;
+            }
+        }
+    }
+}
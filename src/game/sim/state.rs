@@ -0,0 +1,288 @@
+use super::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum EntityKind {
+    Player(i32),
+    Crate,
+    Box,
+    DisableBox,
+    Powerup(Power),
+    WallEffect(Power),
+    DevNull,
+    /// Reached by the players it accepts, see [finish_level](goal::finish_level)
+    Goal(GoalRequirement),
+    /// Has to be covered by a box for the level to be completed
+    Target,
+    /// Tile on the wall its rotation points to, linked to the other portal of the same pair
+    Portal(i32),
+    /// Stays pressed once a movable entity gets on it, activating its channel
+    Button(i32),
+    /// Pressed only while a movable entity is on it
+    Plate(i32),
+    /// Blocks its cell unless something on its channel is pressed
+    Door(i32),
+    /// Belt in the cell of the entities it carries, its rotation points where to
+    Conveyor,
+}
+
+impl EntityKind {
+    pub fn blocking(self) -> bool {
+        self.movable()
+    }
+    pub fn trigger(self) -> bool {
+        matches!(self, Self::Player(_) | Self::Crate | Self::Box)
+    }
+    pub fn pushable(self) -> bool {
+        matches!(self, Self::Box | Self::DisableBox)
+    }
+    pub fn movable(self) -> bool {
+        matches!(
+            self,
+            Self::Player(_) | Self::Crate | Self::Box | Self::DisableBox
+        )
+    }
+    /// Whether this entity has [Side](crate::game::side::Side)s to pick up powers
+    pub fn pickup(self) -> bool {
+        self.movable()
+    }
+    pub fn occupancy(self) -> Occupancy {
+        Occupancy {
+            blocking: self.blocking(),
+            trigger: self.trigger(),
+            pushable: self.pushable(),
+            powerup: matches!(self, Self::Powerup(_)),
+            goal: matches!(self, Self::Goal(_) | Self::Target),
+        }
+    }
+}
+
+/// Index of an entity in a [LevelState], stays the same after others are despawned
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EntityId(pub usize);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EntityState {
+    pub kind: EntityKind,
+    pub coords: GridCoords,
+    pub rotation: Rotation,
+    /// Power attached to every side, indexed by side number
+    pub sides: [Option<Power>; 4],
+    /// Last direction that was input for this entity, used by jump & slide
+    pub input: Direction,
+    /// Gravity directions given by magnets, empty means regular gravity
+    pub override_gravity: Vec<IVec2>,
+    /// Moving sideways will slide instead of rolling
+    pub slide_move: bool,
+    /// Currently sliding (used for the looping slide sound)
+    pub sliding: bool,
+    /// How this entity moves when pushed
+    pub push_motion: PushMotion,
+    /// Came out of a portal, portals ignore it until it leaves the portal tile
+    pub in_portal: bool,
+    /// Cells fallen in a row, a spring launches the entity back by as much
+    pub fall_distance: i32,
+    /// Launched by a spring and not landed yet, falling back does not count
+    pub bouncing: bool,
+    /// Glued entities share a group and only move together, see [PowerDef::glue]
+    ///
+    /// Groups walk, push and fall, a slide power moves them one cell per input.
+    /// Jumps, sliding on, portals, punches and springs leave their members alone.
+    pub group: Option<u32>,
+    /// Turned by a rotator during this turn, it is not turned again until the next one
+    pub turned: bool,
+    /// Pressed button or plate, open door, see [signals::update]
+    pub active: bool,
+}
+
+impl EntityState {
+    pub fn new(kind: EntityKind, coords: GridCoords, rotation: Rotation) -> Self {
+        Self {
+            kind,
+            coords,
+            rotation,
+            sides: [None; 4],
+            input: Direction::None,
+            override_gravity: Vec::new(),
+            slide_move: false,
+            sliding: false,
+            push_motion: PushMotion::Roll,
+            in_portal: false,
+            fall_distance: 0,
+            bouncing: false,
+            group: None,
+            turned: false,
+            active: false,
+        }
+    }
+
+    /// Closed doors are like walls, otherwise it depends on the kind only
+    pub fn occupancy(&self) -> Occupancy {
+        let closed = matches!(self.kind, EntityKind::Door(_)) && !self.active;
+        let occupancy = self.kind.occupancy();
+        Occupancy {
+            blocking: occupancy.blocking || closed,
+            trigger: occupancy.trigger || closed,
+            ..occupancy
+        }
+    }
+
+    /// Gravity directions applied to this entity, overridden ones or just down
+    pub fn gravity(&self) -> &[IVec2] {
+        if self.override_gravity.is_empty() {
+            &[IVec2::NEG_Y]
+        } else {
+            &self.override_gravity
+        }
+    }
+}
+
+/// Settings of a level that stay the same while playing
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LevelRules {
+    /// How many entities in a row can be pushed at once, unlimited if not set
+    pub push_strength: Option<usize>,
+    /// Which players have to reach a goal to complete the level
+    pub objective: Objective,
+}
+
+/// Full state of a level at some point between turns
+#[derive(Debug, Clone, Default)]
+pub struct LevelState {
+    pub rules: LevelRules,
+    /// Shared by every state of the level, use [Arc::make_mut] to change it
+    pub cells: Arc<HashMap<GridCoords, Cell>>,
+    /// Despawned entities leave a `None` so that [EntityId]s stay valid
+    entities: Vec<Option<EntityState>>,
+    grid: Grid<EntityId>,
+    pub selected: Option<EntityId>,
+    /// Set once a player has reached the goal
+    pub completed: bool,
+    /// Player that touched a hazard or fell out of the level, nothing happens anymore
+    pub failed: Option<EntityId>,
+}
+
+/// The grid is not compared since it is derived from the entities
+impl PartialEq for LevelState {
+    fn eq(&self, other: &Self) -> bool {
+        self.rules == other.rules
+            && self.cells == other.cells
+            && self.entities == other.entities
+            && self.selected == other.selected
+            && self.completed == other.completed
+            && self.failed == other.failed
+    }
+}
+
+impl LevelState {
+    pub fn spawn(&mut self, entity: EntityState) -> EntityId {
+        let id = EntityId(self.entities.len());
+        self.grid.insert(id, entity.coords, entity.occupancy());
+        self.entities.push(Some(entity));
+        id
+    }
+
+    pub fn despawn(&mut self, id: EntityId) {
+        self.entities[id.0] = None;
+        self.grid.remove(id);
+    }
+
+    /// Coords must only be changed through here to keep the grid up to date
+    pub fn move_entity(&mut self, id: EntityId, coords: GridCoords, rotation: Rotation) {
+        if let Some(entity) = self.get_mut(id) {
+            entity.coords = coords;
+            entity.rotation = rotation;
+            self.grid.move_to(id, coords);
+        }
+    }
+
+    /// Active state must only be changed through here, doors block depending on it
+    pub fn set_active(&mut self, id: EntityId, active: bool) {
+        if let Some(entity) = self.get_mut(id) {
+            entity.active = active;
+            let (coords, occupancy) = (entity.coords, entity.occupancy());
+            self.grid.insert(id, coords, occupancy);
+        }
+    }
+
+    pub fn get(&self, id: EntityId) -> Option<&EntityState> {
+        self.entities.get(id.0)?.as_ref()
+    }
+
+    /// Use [Self::move_entity] to change coords
+    pub fn get_mut(&mut self, id: EntityId) -> Option<&mut EntityState> {
+        self.entities.get_mut(id.0)?.as_mut()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (EntityId, &EntityState)> {
+        self.entities
+            .iter()
+            .enumerate()
+            .filter_map(|(index, entity)| Some((EntityId(index), entity.as_ref()?)))
+    }
+
+    pub fn ids(&self) -> Vec<EntityId> {
+        self.iter().map(|(id, _)| id).collect()
+    }
+
+    /// All entities at given coords
+    pub fn at(&self, coords: GridCoords) -> impl Iterator<Item = (EntityId, &EntityState)> {
+        self.grid
+            .occupants(coords)
+            .filter_map(|(id, _)| Some((id, self.get(id)?)))
+    }
+
+    /// Finds an entity at given coords matching the filter
+    pub fn find_at(
+        &self,
+        coords: GridCoords,
+        filter: impl Fn(&EntityState) -> bool,
+    ) -> Option<EntityId> {
+        self.at(coords)
+            .find(|(_, entity)| filter(entity))
+            .map(|(id, _)| id)
+    }
+
+    /// Level geometry at given coords, empty cells do nothing
+    pub fn cell(&self, coords: GridCoords) -> Cell {
+        self.cells.get(&coords).copied().unwrap_or_default()
+    }
+
+    /// Whether moving by `dir` into `coords` is blocked, see [Cell::blocks]
+    pub fn is_blocked(&self, coords: GridCoords, dir: IVec2) -> bool {
+        self.cell(coords).blocks(dir) || self.grid.is_blocked(coords)
+    }
+
+    /// Checks whether a cell activates side effects
+    pub fn is_trigger(&self, coords: GridCoords) -> bool {
+        self.cell(coords).trigger || self.grid.is_trigger(coords)
+    }
+
+    /// Verifies that the grid matches the entities
+    pub fn check_grid(&self) -> Result<(), String> {
+        self.grid.check(
+            self.iter()
+                .map(|(id, entity)| (id, entity.coords, entity.occupancy())),
+        )
+    }
+
+    /// Selects another player, `dir` is the offset in the list of players sorted by index
+    pub fn switch_player(&mut self, dir: i32) {
+        let mut players: Vec<(i32, (i32, i32), EntityId)> = self
+            .iter()
+            .filter_map(|(id, entity)| match entity.kind {
+                EntityKind::Player(index) => Some((index, (entity.coords.x, entity.coords.y), id)),
+                _ => None,
+            })
+            .collect();
+        if players.is_empty() {
+            return;
+        }
+        players.sort();
+        let selected = players
+            .iter()
+            .position(|&(.., id)| Some(id) == self.selected);
+        let to_select = (selected.unwrap_or(0) as i32 + players.len() as i32 + dir)
+            .rem_euclid(players.len() as i32);
+        self.selected = Some(players[to_select as usize].2);
+    }
+}
@@ -0,0 +1,972 @@
+//! Small hand made levels pinning down how the mechanics work
+
+use bevy_ecs_ldtk::ldtk::{self, FieldValue};
+
+use super::*;
+
+/// Builds a level from rows of cells, top row first
+///
+/// `#` is a wall, `~` is ice, `^` a hazard, `=` a spring and `-` a one-way platform.
+fn level(rows: &[&str]) -> LevelState {
+    load_defs(concat!(env!("CARGO_MANIFEST_DIR"), "/assets")).unwrap();
+    let mut state = LevelState::default();
+    let cell = |identifier| {
+        CellDef::all()
+            .iter()
+            .find(|def| def.identifier == identifier)
+            .unwrap()
+            .cell
+    };
+    for (row, line) in rows.iter().enumerate() {
+        for (x, char) in line.chars().enumerate() {
+            let cell = match char {
+                '#' => cell("block"),
+                '~' => cell("ice"),
+                '^' => cell("hazard"),
+                '=' => cell("spring"),
+                '-' => cell("oneWay"),
+                _ => continue,
+            };
+            let y = (rows.len() - 1 - row) as i32;
+            Arc::make_mut(&mut state.cells).insert(GridCoords::new(x as i32, y), cell);
+        }
+    }
+    state
+}
+
+fn power(name: &str) -> Power {
+    Power::from_name(name).unwrap()
+}
+
+/// Spawns a player at `(x, y)`, selected if it is the first one
+fn player(state: &mut LevelState, x: i32, y: i32, sides: [Option<&str>; 4]) -> EntityId {
+    let mut entity = EntityState::new(EntityKind::Player(0), GridCoords::new(x, y), Rotation(0));
+    entity.sides = sides.map(|side| side.map(power));
+    let id = state.spawn(entity);
+    state.selected.get_or_insert(id);
+    id
+}
+
+fn spawn(state: &mut LevelState, kind: EntityKind, x: i32, y: i32) -> EntityId {
+    state.spawn(EntityState::new(kind, GridCoords::new(x, y), Rotation(0)))
+}
+
+/// Where the entity is and how it is rotated, rotation in `0..4`
+fn pose(state: &LevelState, id: EntityId) -> (i32, i32, i32) {
+    let entity = state.get(id).unwrap();
+    let rotation = entity.rotation.normalized().0;
+    (entity.coords.x, entity.coords.y, rotation)
+}
+
+/// Moves the selected player and plays the turn, checking the grid along the way
+fn turn(state: &LevelState, input: Input) -> (LevelState, Vec<Vec<MoveEvent>>) {
+    let (state, history) = play(state, input, MAX_SETTLE_STEPS);
+    state.check_grid().unwrap();
+    (state, history)
+}
+
+fn kinds(events: &[MoveEvent]) -> Vec<MoveKind> {
+    events.iter().map(|event| event.kind).collect()
+}
+
+const ROOM: [&str; 6] = [
+    "########", //
+    "#......#", //
+    "#......#", //
+    "#......#", //
+    "#......#", //
+    "########", //
+];
+
+#[test]
+fn walking_rolls_the_player() {
+    let mut state = level(&ROOM);
+    let id = player(&mut state, 2, 1, [None; 4]);
+    let (state, history) = turn(&state, Input::Move(Direction::Right));
+    assert_eq!(kinds(&history[0]), [MoveKind::Walk]);
+    assert_eq!(pose(&state, id), (3, 1, 3));
+    let (state, _) = turn(&state, Input::Move(Direction::Left));
+    assert_eq!(pose(&state, id), (2, 1, 0));
+}
+
+#[test]
+fn walking_into_a_wall_rotates_in_place() {
+    let mut state = level(&ROOM);
+    let id = player(&mut state, 1, 1, [None; 4]);
+    let (state, _) = turn(&state, Input::Move(Direction::Left));
+    assert_eq!(pose(&state, id), (1, 1, 1));
+}
+
+#[test]
+fn jump_goes_two_cells_up_and_steers_with_the_input() {
+    let mut state = level(&ROOM);
+    let id = player(&mut state, 2, 1, [Some("Jump"), None, None, None]);
+    let (straight, events) = step(&state, Input::Wait);
+    assert_eq!(kinds(&events), [MoveKind::Jump]);
+    assert_eq!(pose(&straight, id), (2, 3, 0));
+
+    state.get_mut(id).unwrap().input = Direction::Right;
+    let (steered, _) = step(&state, Input::Wait);
+    assert_eq!(pose(&steered, id), (3, 3, 3));
+}
+
+#[test]
+fn jump_is_cut_short_by_the_ceiling_but_still_rolls() {
+    let mut state = level(&[
+        "#####", //
+        "#...#", //
+        "#...#", //
+        "#####", //
+    ]);
+    let id = player(&mut state, 2, 1, [Some("Jump"), None, None, None]);
+    state.get_mut(id).unwrap().input = Direction::Right;
+    let (state, _) = step(&state, Input::Wait);
+    assert_eq!(pose(&state, id), (2, 2, 3));
+}
+
+#[test]
+fn wall_jump_goes_two_cells_away_from_the_wall() {
+    let mut state = level(&ROOM);
+    // Side 1 faces left when standing upright
+    let id = player(&mut state, 1, 3, [None, Some("Jump"), None, None]);
+    let (state, events) = step(&state, Input::Wait);
+    assert_eq!(kinds(&events), [MoveKind::Jump]);
+    assert_eq!(pose(&state, id), (3, 3, 0));
+}
+
+#[test]
+fn wall_jump_is_not_held_back_by_the_ground() {
+    let mut state = level(&ROOM);
+    let id = player(&mut state, 1, 1, [None, Some("Jump"), None, None]);
+    let (state, events) = step(&state, Input::Wait);
+    assert_eq!(kinds(&events), [MoveKind::Jump]);
+    assert_eq!(pose(&state, id), (3, 1, 0));
+}
+
+#[test]
+fn last_jump_wins_when_several_sides_touch_walls() {
+    let mut state = level(&ROOM);
+    let id = player(&mut state, 1, 4, [None, Some("Jump"), Some("Jump"), None]);
+    let (state, events) = step(&state, Input::Wait);
+    assert_eq!(kinds(&events), [MoveKind::Jump]);
+    // Sides are ordered top to bottom, so the jump off the wall is made instead of down
+    assert_eq!(pose(&state, id), (3, 4, 0));
+}
+
+#[test]
+fn slide_moves_without_rolling_until_blocked() {
+    let mut state = level(&ROOM);
+    let id = player(&mut state, 1, 1, [Some("Slide"), None, None, None]);
+    let (state, _) = turn(&state, Input::Wait);
+    assert!(state.get(id).unwrap().slide_move);
+
+    let (state, history) = turn(&state, Input::Move(Direction::Right));
+    assert!(
+        history[0].is_empty(),
+        "the move itself is made by the slide"
+    );
+    assert!(history[1..]
+        .iter()
+        .all(|events| kinds(events) == [MoveKind::Slide]));
+    assert_eq!(pose(&state, id), (6, 1, 0));
+}
+
+#[test]
+fn slide_rolls_off_an_edge() {
+    let mut state = level(&[
+        "######", //
+        "#....#", //
+        "#....#", //
+        "#.#..#", //
+        "######", //
+    ]);
+    let id = player(&mut state, 2, 2, [Some("Slide"), None, None, None]);
+    let (state, _) = turn(&state, Input::Wait);
+    let (state, history) = turn(&state, Input::Move(Direction::Right));
+    assert_eq!(kinds(&history[1]), [MoveKind::Slide]);
+    assert_eq!(pose(&state, id), (3, 1, 3));
+}
+
+#[test]
+fn magnet_holds_the_player_on_the_wall() {
+    let mut state = level(&ROOM);
+    // Magnet facing left, the player is put next to the wall mid air
+    let id = player(&mut state, 1, 3, [None, Some("Magnet"), None, None]);
+    let (state, _) = turn(&state, Input::Wait);
+    assert_eq!(pose(&state, id), (1, 3, 0));
+    assert_eq!(state.get(id).unwrap().override_gravity, [IVec2::NEG_X]);
+
+    // Moves are made relative to the magnet, rolling down the wall turns the magnet away
+    let (state, history) = turn(&state, Input::Move(Direction::Right));
+    assert_eq!(history[0][0].coords, GridCoords::new(1, 2));
+    assert_eq!(pose(&state, id), (1, 1, 3));
+}
+
+#[test]
+fn magnet_turns_around_corners() {
+    let mut state = level(&[
+        "#######", //
+        "#.....#", //
+        "#..#..#", //
+        "#..#..#", //
+        "#..#..#", //
+        "#######", //
+    ]);
+    // Magnet facing right, onto the pillar
+    let id = player(&mut state, 2, 3, [None, None, None, Some("Magnet")]);
+    let (state, _) = turn(&state, Input::Wait);
+    assert_eq!(state.get(id).unwrap().override_gravity, [IVec2::X]);
+    let (state, history) = turn(&state, Input::Move(Direction::Right));
+    assert_eq!(kinds(&history[0]), [MoveKind::Walk]);
+    assert_eq!(pose(&state, id), (3, 4, 2));
+}
+
+#[test]
+fn frame_without_input_drops_the_magnet_until_the_next_turn() {
+    let mut state = level(&ROOM);
+    let id = player(&mut state, 1, 3, [None, Some("Magnet"), None, None]);
+    let (held, _) = turn(&state, Input::Wait);
+
+    let (released, history) = turn(&held, Input::Move(Direction::None));
+    assert_eq!(history, [vec![]]);
+    assert!(released.get(id).unwrap().override_gravity.is_empty());
+    assert_eq!(pose(&released, id), (1, 3, 0));
+
+    // Without the magnet the move is made on the ground gravity and the player falls
+    let (state, _) = turn(&released, Input::Move(Direction::Right));
+    assert_eq!(pose(&state, id), (2, 1, 3));
+}
+
+#[test]
+fn switching_players_keeps_the_direction_held_while_switching() {
+    let mut state = level(&ROOM);
+    let first = player(&mut state, 1, 3, [None, Some("Magnet"), None, None]);
+    let second = player(&mut state, 4, 1, [None; 4]);
+    state.get_mut(second).unwrap().kind = EntityKind::Player(1);
+    state.get_mut(second).unwrap().input = Direction::Right;
+    let (state, _) = turn(&state, Input::Wait);
+
+    // The first one only takes the direction, the second one moves with the input it was left with
+    let frame = [Input::Move(Direction::Left), Input::SwitchPlayer(1)];
+    let (held, history) = play_frame(&state, &frame, MAX_SETTLE_STEPS);
+    assert_eq!(held.selected, Some(second));
+    assert_eq!(held.get(first).unwrap().input, Direction::Left);
+    assert_eq!(pose(&held, first), (1, 3, 0));
+    assert_eq!(kinds(&history[0]), [MoveKind::Walk]);
+    assert_eq!(pose(&held, second), (5, 1, 3));
+
+    // Switching with nothing pressed releases the input and drops the magnets
+    let mut state = state;
+    state.get_mut(second).unwrap().input = Direction::None;
+    let frame = [Input::Move(Direction::None), Input::SwitchPlayer(1)];
+    let (released, history) = play_frame(&state, &frame, MAX_SETTLE_STEPS);
+    assert_eq!(history, [vec![]]);
+    assert_eq!(released.selected, Some(second));
+    assert_eq!(released.get(first).unwrap().input, Direction::None);
+    assert!(released.get(first).unwrap().override_gravity.is_empty());
+}
+
+#[test]
+fn push_moves_the_box_and_rolls_it() {
+    let mut state = level(&ROOM);
+    let id = player(&mut state, 1, 1, [None; 4]);
+    let pushed = spawn(&mut state, EntityKind::Box, 2, 1);
+    let (state, history) = turn(&state, Input::Move(Direction::Right));
+    assert_eq!(kinds(&history[0]), [MoveKind::Push, MoveKind::Walk]);
+    assert_eq!(pose(&state, pushed), (3, 1, 3));
+    assert_eq!(pose(&state, id), (2, 1, 3));
+}
+
+#[test]
+fn rolled_box_turns_its_sides_with_it() {
+    let mut state = level(&ROOM);
+    player(&mut state, 1, 1, [None; 4]);
+    let pushed = spawn(&mut state, EntityKind::Box, 2, 1);
+    state.get_mut(pushed).unwrap().sides[0] = Some(power("Jump"));
+    let (state, _) = step(&state, Input::Move(Direction::Right));
+    let pushed = state.get(pushed).unwrap();
+    // The bottom side rolled over to face the player
+    assert_eq!(side_vec(pushed.rotation.0, 0), IVec2::NEG_X);
+    assert_eq!(pushed.sides[0], Some(power("Jump")));
+}
+
+#[test]
+fn sliding_boxes_and_boxes_on_ice_keep_their_rotation() {
+    let mut state = level(&ROOM);
+    let id = player(&mut state, 1, 1, [None; 4]);
+    let pushed = spawn(&mut state, EntityKind::Box, 2, 1);
+    state.get_mut(pushed).unwrap().push_motion = PushMotion::Slide;
+    let (slid, history) = turn(&state, Input::Move(Direction::Right));
+    assert_eq!(kinds(&history[0]), [MoveKind::Push, MoveKind::Walk]);
+    assert_eq!(pose(&slid, pushed), (3, 1, 0));
+    assert_eq!(pose(&slid, id), (2, 1, 3));
+
+    let mut state = level(&[
+        "######", //
+        "#....#", //
+        "#....#", //
+        "##~###", //
+    ]);
+    player(&mut state, 1, 1, [None; 4]);
+    let pushed = spawn(&mut state, EntityKind::Box, 2, 1);
+    let (state, _) = turn(&state, Input::Move(Direction::Right));
+    assert_eq!(pose(&state, pushed), (3, 1, 0));
+}
+
+#[test]
+fn push_is_blocked_by_walls_and_push_strength() {
+    let mut state = level(&ROOM);
+    let id = player(&mut state, 3, 1, [None; 4]);
+    spawn(&mut state, EntityKind::Box, 4, 1);
+    spawn(&mut state, EntityKind::Box, 5, 1);
+    let (pushed, _) = turn(&state, Input::Move(Direction::Right));
+    assert_eq!(pose(&pushed, id), (4, 1, 3));
+    let (blocked, _) = turn(&pushed, Input::Move(Direction::Right));
+    assert_eq!(pose(&blocked, id), (4, 1, 2), "rotated in place");
+
+    state.rules.push_strength = Some(1);
+    let (weak, _) = turn(&state, Input::Move(Direction::Right));
+    assert_eq!(pose(&weak, id), (3, 1, 3));
+}
+
+#[test]
+fn crates_can_not_be_pushed() {
+    let mut state = level(&ROOM);
+    let id = player(&mut state, 1, 1, [None; 4]);
+    let blocker = spawn(&mut state, EntityKind::Crate, 2, 1);
+    let (state, _) = turn(&state, Input::Move(Direction::Right));
+    assert_eq!(pose(&state, id), (1, 1, 3));
+    assert_eq!(pose(&state, blocker), (2, 1, 0));
+}
+
+fn moved(entity: EntityId, kind: MoveKind, x: i32, y: i32) -> MoveEvent {
+    MoveEvent {
+        entity,
+        kind,
+        coords: GridCoords::new(x, y),
+        rotation: Rotation(0),
+        sfx: None,
+        end_sfx: None,
+        vfx: None,
+        end_vfx: None,
+        teleport: false,
+        input: None,
+    }
+}
+
+#[test]
+fn conflicts_keep_the_highest_kind_of_an_entity() {
+    let mut state = level(&ROOM);
+    let id = spawn(&mut state, EntityKind::Box, 2, 2);
+    let events = resolve_conflicts(
+        &state,
+        vec![
+            moved(id, MoveKind::Jump, 2, 4),
+            moved(id, MoveKind::Fall, 2, 1),
+            moved(id, MoveKind::Jump, 3, 4),
+            moved(id, MoveKind::Rotate, 2, 2),
+        ],
+    );
+    assert_eq!(events, [moved(id, MoveKind::Jump, 3, 4)], "the last jump");
+}
+
+#[test]
+fn conflicts_keep_entities_that_stay_in_their_cells() {
+    let mut state = level(&ROOM);
+    let staying = spawn(&mut state, EntityKind::Box, 3, 1);
+    let blocked = spawn(&mut state, EntityKind::Box, 2, 1);
+    let behind = spawn(&mut state, EntityKind::Box, 1, 1);
+    let events = resolve_conflicts(
+        &state,
+        vec![
+            moved(staying, MoveKind::Rotate, 3, 1),
+            moved(blocked, MoveKind::Walk, 3, 1),
+            moved(behind, MoveKind::Push, 2, 1),
+        ],
+    );
+    assert_eq!(
+        events,
+        [moved(staying, MoveKind::Rotate, 3, 1)],
+        "the rejected move blocks the one behind it"
+    );
+}
+
+#[test]
+fn conflicts_over_a_cell_go_to_the_highest_kind_then_the_lowest_cell() {
+    let mut state = level(&ROOM);
+    let falling = spawn(&mut state, EntityKind::Box, 3, 2);
+    let walking = spawn(&mut state, EntityKind::Box, 2, 1);
+    let events = resolve_conflicts(
+        &state,
+        vec![
+            moved(falling, MoveKind::Fall, 3, 1),
+            moved(walking, MoveKind::Walk, 3, 1),
+        ],
+    );
+    assert_eq!(events, [moved(walking, MoveKind::Walk, 3, 1)]);
+
+    // Spawned first or not, the lower one wins a tie
+    let mut state = level(&ROOM);
+    let high = spawn(&mut state, EntityKind::Box, 2, 3);
+    let low = spawn(&mut state, EntityKind::Box, 1, 2);
+    let events = resolve_conflicts(
+        &state,
+        vec![
+            moved(high, MoveKind::Jump, 2, 2),
+            moved(low, MoveKind::Jump, 2, 2),
+        ],
+    );
+    assert_eq!(events, [moved(low, MoveKind::Jump, 2, 2)]);
+}
+
+#[test]
+fn conflicts_reject_swaps() {
+    let mut state = level(&ROOM);
+    let left = spawn(&mut state, EntityKind::Box, 2, 1);
+    let right = spawn(&mut state, EntityKind::Box, 3, 1);
+    let events = resolve_conflicts(
+        &state,
+        vec![
+            moved(left, MoveKind::Walk, 3, 1),
+            moved(right, MoveKind::Push, 2, 1),
+        ],
+    );
+    assert_eq!(events, []);
+}
+
+#[test]
+fn conflicts_reject_whole_groups() {
+    let mut state = level(&ROOM);
+    spawn(&mut state, EntityKind::Crate, 3, 2);
+    let glued = [
+        spawn(&mut state, EntityKind::Box, 2, 1),
+        spawn(&mut state, EntityKind::Box, 2, 2),
+    ];
+    for id in glued {
+        state.get_mut(id).unwrap().group = Some(0);
+    }
+    let events = resolve_conflicts(
+        &state,
+        vec![
+            moved(glued[0], MoveKind::Walk, 3, 1),
+            moved(glued[1], MoveKind::Walk, 3, 2),
+        ],
+    );
+    assert_eq!(events, [], "the bottom one is free but glued");
+}
+
+#[test]
+fn plate_opens_a_door_and_entities_fall_through_in_the_same_step() {
+    let mut state = level(&ROOM);
+    let id = player(&mut state, 1, 1, [None; 4]);
+    spawn(&mut state, EntityKind::Plate(3), 2, 1);
+    let door = spawn(&mut state, EntityKind::Door(3), 4, 1);
+    let other_channel = spawn(&mut state, EntityKind::Door(2), 5, 1);
+    let falling = spawn(&mut state, EntityKind::Box, 4, 2);
+    let (state, _) = turn(&state, Input::Wait);
+    assert_eq!(
+        pose(&state, falling),
+        (4, 2, 0),
+        "held up by the closed door"
+    );
+
+    let (state, history) = turn(&state, Input::Move(Direction::Right));
+    assert_eq!(pose(&state, id), (2, 1, 3));
+    assert_eq!(
+        history[1]
+            .iter()
+            .map(|event| (event.entity, event.kind, event.coords))
+            .collect::<Vec<_>>(),
+        [(falling, MoveKind::Fall, GridCoords::new(4, 1))]
+    );
+    assert!(state.get(door).unwrap().active);
+    assert!(!state.get(other_channel).unwrap().active);
+
+    // Stays open while the box is in it, even with the plate released
+    let (state, _) = turn(&state, Input::Move(Direction::Left));
+    assert_eq!(pose(&state, id), (1, 1, 0));
+    assert!(state.get(door).unwrap().active);
+}
+
+/// Spawns a portal on the wall in direction `wall`
+fn portal(state: &mut LevelState, pair: i32, x: i32, y: i32, wall: IVec2) -> EntityId {
+    let rotation = Rotation(vec_to_rot(wall));
+    state.spawn(EntityState::new(
+        EntityKind::Portal(pair),
+        GridCoords::new(x, y),
+        rotation,
+    ))
+}
+
+const HALL: [&str; 8] = [
+    "##########", //
+    "#........#", //
+    "#........#", //
+    "#........#", //
+    "#........#", //
+    "#........#", //
+    "#........#", //
+    "##########", //
+];
+
+#[test]
+fn floor_portal_launches_out_of_a_wall_portal_by_the_fall() {
+    let mut state = level(&HALL);
+    let id = player(&mut state, 2, 4, [Some("Portal"), None, None, None]);
+    state.get_mut(id).unwrap().input = Direction::Right;
+    portal(&mut state, 0, 2, 1, IVec2::NEG_Y);
+    portal(&mut state, 0, 1, 4, IVec2::NEG_X);
+    let (state, history) = turn(&state, Input::Wait);
+
+    let teleport = history
+        .iter()
+        .flatten()
+        .find(|event| event.kind == MoveKind::Teleport)
+        .unwrap();
+    // Fell three cells, so it flies three cells away from the wall
+    assert_eq!(teleport.coords, GridCoords::new(4, 4));
+    assert_eq!(
+        teleport.input,
+        Some(Direction::None),
+        "the input would point up"
+    );
+    // Turned a quarter with the portal side leading, then it falls down
+    assert_eq!(pose(&state, id), (4, 1, 1));
+}
+
+#[test]
+fn wall_portals_carry_the_walk_and_turn_the_input() {
+    let mut state = level(&HALL);
+    // Side 2 faces right after rolling right
+    let id = player(&mut state, 7, 1, [None, None, Some("Portal"), None]);
+    portal(&mut state, 0, 8, 1, IVec2::X);
+    portal(&mut state, 0, 8, 4, IVec2::X);
+    let (state, _) = turn(&state, Input::Move(Direction::Right));
+    // Out of the right wall, turned around and walking back left
+    assert_eq!(pose(&state, id), (7, 1, 1));
+    assert_eq!(state.get(id).unwrap().input, Direction::Left);
+
+    let mut state = level(&HALL);
+    let id = player(&mut state, 7, 1, [None, None, Some("Portal"), None]);
+    portal(&mut state, 0, 8, 1, IVec2::X);
+    portal(&mut state, 0, 1, 4, IVec2::NEG_X);
+    let (state, _) = turn(&state, Input::Move(Direction::Right));
+    // Out of the left wall, still going right
+    assert_eq!(pose(&state, id), (2, 1, 3));
+    assert_eq!(state.get(id).unwrap().input, Direction::Right);
+}
+
+/// Puts the entities into one glue group, like a [PowerDef::glue] side would
+fn glue(state: &mut LevelState, ids: &[EntityId]) {
+    for &id in ids {
+        state.get_mut(id).unwrap().group = Some(0);
+    }
+}
+
+#[test]
+fn glue_side_groups_the_entity_it_touches() {
+    let mut state = level(&ROOM);
+    // Side 3 faces right when standing upright
+    let id = player(&mut state, 1, 1, [None, None, None, Some("Glue")]);
+    let touched = spawn(&mut state, EntityKind::Box, 2, 1);
+    let (state, _) = turn(&state, Input::Wait);
+    assert_eq!(state.group_of(id), [id, touched]);
+    let (state, _) = turn(&state, Input::Move(Direction::Right));
+    // Rolls over the box, which ends up below
+    assert_eq!(pose(&state, id), (3, 2, 3));
+    assert_eq!(pose(&state, touched), (3, 1, 3));
+}
+
+#[test]
+fn glued_group_rolls_over_its_front_bottom_corner() {
+    let mut state = level(&ROOM);
+    let id = player(&mut state, 2, 1, [None; 4]);
+    let on_top = spawn(&mut state, EntityKind::Box, 2, 2);
+    glue(&mut state, &[id, on_top]);
+    let (state, history) = turn(&state, Input::Move(Direction::Right));
+    assert_eq!(kinds(&history[0]), [MoveKind::Walk, MoveKind::Walk]);
+    // The tower tips over to the right, the top ends up in front
+    assert_eq!(pose(&state, id), (3, 1, 3));
+    assert_eq!(pose(&state, on_top), (4, 1, 3));
+
+    // Tips back over the front bottom corner to the left, standing where it started
+    let (state, _) = turn(&state, Input::Move(Direction::Left));
+    assert_eq!(pose(&state, id), (2, 1, 0));
+    assert_eq!(pose(&state, on_top), (2, 2, 0));
+}
+
+#[test]
+fn glued_group_falls_together_unless_a_member_is_held() {
+    let mut state = level(&[
+        "########", //
+        "#......#", //
+        "#......#", //
+        "#..#...#", //
+        "########", //
+    ]);
+    let id = player(&mut state, 2, 2, [None; 4]);
+    let next = spawn(&mut state, EntityKind::Box, 3, 2);
+    glue(&mut state, &[id, next]);
+    let (held, events) = step(&state, Input::Wait);
+    assert_eq!(events, []);
+    assert_eq!(pose(&held, id), (2, 2, 0));
+
+    state.move_entity(next, GridCoords::new(1, 3), Rotation(0));
+    state.move_entity(id, GridCoords::new(2, 3), Rotation(0));
+    let (state, history) = turn(&state, Input::Wait);
+    assert!(history
+        .iter()
+        .all(|events| kinds(events) == [MoveKind::Fall, MoveKind::Fall]));
+    assert_eq!(pose(&state, id), (2, 1, 0));
+    assert_eq!(pose(&state, next), (1, 1, 0));
+}
+
+#[test]
+fn glued_group_slides_one_cell_per_move_and_does_not_jump() {
+    let mut state = level(&ROOM);
+    let id = player(&mut state, 2, 1, [Some("Slide"), None, None, None]);
+    let on_top = spawn(&mut state, EntityKind::Box, 2, 2);
+    glue(&mut state, &[id, on_top]);
+    let (state, _) = turn(&state, Input::Wait);
+    let (state, history) = turn(&state, Input::Move(Direction::Right));
+    assert_eq!(history.len(), 1, "the slide does not carry on");
+    assert_eq!(pose(&state, id), (3, 1, 0));
+    assert_eq!(pose(&state, on_top), (3, 2, 0));
+
+    let mut state = level(&ROOM);
+    let id = player(&mut state, 2, 1, [Some("Jump"), None, None, None]);
+    let on_top = spawn(&mut state, EntityKind::Box, 2, 2);
+    glue(&mut state, &[id, on_top]);
+    let (_, events) = step(&state, Input::Wait);
+    assert_eq!(events, []);
+}
+
+/// Goal with the default fields, for any player standing upright
+const GOAL: EntityKind = EntityKind::Goal(GoalRequirement {
+    player: None,
+    rotation: Some(0),
+});
+
+fn completed(state: &LevelState) -> bool {
+    let mut state = state.clone();
+    goal::finish_level(&mut state);
+    state.completed
+}
+
+#[test]
+fn all_players_objective_needs_every_player_on_a_goal() {
+    let mut state = level(&ROOM);
+    player(&mut state, 1, 1, [None; 4]);
+    spawn(&mut state, EntityKind::Player(1), 4, 1);
+    spawn(&mut state, GOAL, 1, 1);
+    assert!(completed(&state));
+
+    state.rules.objective = Objective::AllPlayers;
+    assert!(!completed(&state));
+    spawn(&mut state, GOAL, 4, 1);
+    assert!(completed(&state));
+}
+
+#[test]
+fn unknown_objective_fails_to_load() {
+    let objective = |value: &str| ldtk::FieldInstance {
+        identifier: "Objective".to_owned(),
+        tile: None,
+        field_instance_type: "LocalEnum.Objective".to_owned(),
+        value: FieldValue::Enum(Some(value.to_owned())),
+        def_uid: 0,
+        real_editor_values: Vec::new(),
+    };
+    let mut level = ldtk::Level {
+        identifier: "Test".to_owned(),
+        field_instances: vec![objective("AllPlayers")],
+        ..Default::default()
+    };
+    let state = LevelState::from_ldtk(&level, &CellTable::default()).unwrap();
+    assert_eq!(state.rules.objective, Objective::AllPlayers);
+
+    level.field_instances = vec![objective("Everyone")];
+    let error = LevelState::from_ldtk(&level, &CellTable::default()).unwrap_err();
+    assert!(error.starts_with("Test: "), "{error}");
+}
+
+#[test]
+fn unknown_int_grid_values_fail_to_load() {
+    load_defs(concat!(env!("CARGO_MANIFEST_DIR"), "/assets")).unwrap();
+    let project = load_project(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/world.ldtk")).unwrap();
+    let cells = CellTable::from_ldtk(&project);
+    let mut level = iter_levels(&project).next().unwrap().clone();
+    assert!(LevelState::from_ldtk(&level, &cells).is_ok());
+
+    let layers = level.layer_instances.as_ref().unwrap();
+    let layer = layers
+        .iter()
+        .position(|layer| layer.layer_instance_type == ldtk::Type::IntGrid)
+        .unwrap();
+    let csv = &layers[layer].int_grid_csv;
+    let index = csv.iter().position(|&value| value != 0).unwrap();
+    for (value, message) in [(2, "\"slopeLT\""), (99, "has no identifier")] {
+        level.layer_instances.as_mut().unwrap()[layer].int_grid_csv[index] = value;
+        let error = LevelState::from_ldtk(&level, &cells).unwrap_err();
+        assert!(
+            error.starts_with(&format!("{}: ", level.identifier)),
+            "{error}"
+        );
+        assert!(error.contains(message), "{error}");
+    }
+}
+
+#[test]
+fn goals_only_accept_their_player_and_rotation() {
+    let goal = |player, rotation| EntityKind::Goal(GoalRequirement { player, rotation });
+    let mut state = level(&ROOM);
+    let id = player(&mut state, 1, 1, [None; 4]);
+    let other = spawn(&mut state, goal(Some(1), Some(0)), 1, 1);
+    assert!(!completed(&state), "goal of another player");
+    state.despawn(other);
+
+    state.move_entity(id, GridCoords::new(1, 1), Rotation(3));
+    spawn(&mut state, goal(None, Some(0)), 1, 1);
+    assert!(!completed(&state), "player on its side");
+    spawn(&mut state, goal(Some(0), None), 1, 1);
+    assert!(completed(&state), "any rotation");
+}
+
+#[test]
+fn targets_have_to_be_covered_as_well_as_goals_reached() {
+    let mut state = level(&ROOM);
+    player(&mut state, 1, 1, [None; 4]);
+    spawn(&mut state, EntityKind::Target, 3, 1);
+    let pushed = spawn(&mut state, EntityKind::Box, 2, 1);
+    assert!(!completed(&state));
+    let (covered, _) = turn(&state, Input::Move(Direction::Right));
+    assert!(covered.completed);
+
+    spawn(&mut state, GOAL, 5, 1);
+    state.move_entity(pushed, GridCoords::new(3, 1), Rotation(0));
+    assert!(!completed(&state), "the player is not on the goal");
+}
+
+#[test]
+fn punch_stops_before_a_wall_and_the_target_falls_after() {
+    let mut state = level(&[
+        "######", //
+        "#....#", //
+        "#....#", //
+        "###..#", //
+        "######", //
+    ]);
+    // Side 3 faces right when standing upright
+    player(&mut state, 1, 2, [None, None, None, Some("Punch")]);
+    let punched = spawn(&mut state, EntityKind::Box, 2, 2);
+    let (state, history) = turn(&state, Input::Wait);
+    assert_eq!(kinds(&history[0]), [MoveKind::Push]);
+    assert_eq!(history[0][0].coords, GridCoords::new(4, 2));
+    assert_eq!(kinds(&history[1]), [MoveKind::Fall]);
+    assert_eq!(pose(&state, punched), (4, 1, 0));
+}
+
+#[test]
+fn rotator_turns_the_touched_entity_once_per_turn_with_its_sides() {
+    let mut state = level(&ROOM);
+    player(&mut state, 1, 1, [None, None, None, Some("Rotator")]);
+    let turned = spawn(&mut state, EntityKind::Box, 2, 1);
+    state.get_mut(turned).unwrap().sides[0] = Some(power("Slide"));
+    let (state, history) = turn(&state, Input::Wait);
+    // Still touched after turning, but only turned again in the next turn
+    assert_eq!(kinds(&history.concat()), [MoveKind::Rotate]);
+    assert_eq!(pose(&state, turned), (2, 1, 1));
+    // The bottom side now faces away from the player
+    let entity = state.get(turned).unwrap();
+    assert_eq!(side_vec(entity.rotation.0, 0), IVec2::X);
+    assert_eq!(entity.sides[0], Some(power("Slide")));
+}
+
+#[test]
+fn conveyor_carries_what_rests_on_it() {
+    let mut state = level(&ROOM);
+    player(&mut state, 5, 1, [None; 4]);
+    let carried = spawn(&mut state, EntityKind::Box, 2, 1);
+    // Rotation 1 points right
+    let belt = EntityState::new(EntityKind::Conveyor, GridCoords::new(2, 1), Rotation(1));
+    state.spawn(belt);
+    let (state, history) = turn(&state, Input::Wait);
+    assert_eq!(kinds(&history[0]), [MoveKind::Carry]);
+    assert_eq!(pose(&state, carried), (3, 1, 0));
+}
+
+#[test]
+fn facing_conveyors_never_settle() {
+    let mut state = level(&ROOM);
+    player(&mut state, 5, 1, [None; 4]);
+    spawn(&mut state, EntityKind::Box, 2, 1);
+    for (x, rotation) in [(2, 1), (3, 3)] {
+        let belt = EntityState::new(
+            EntityKind::Conveyor,
+            GridCoords::new(x, 1),
+            Rotation(rotation),
+        );
+        state.spawn(belt);
+    }
+    let (_, history) = turn(&state, Input::Wait);
+    assert_eq!(history.len(), MAX_SETTLE_STEPS + 1);
+}
+
+#[test]
+fn touching_a_hazard_or_falling_out_of_the_level_fails_it() {
+    let hole = [
+        "#.....#", //
+        "#.....#", //
+        "#..^..#", //
+        "#####.#", //
+    ];
+    let mut state = level(&hole);
+    let id = player(&mut state, 1, 1, [None; 4]);
+    let (state, _) = turn(&state, Input::Move(Direction::Right));
+    assert_eq!(pose(&state, id), (2, 1, 3));
+    assert_eq!(state.failed, Some(id));
+    let (state, events) = step(&state, Input::Move(Direction::Left));
+    assert!(events.is_empty(), "nothing moves after failing");
+    assert_eq!(pose(&state, id), (2, 1, 3));
+
+    let mut state = level(&hole);
+    player(&mut state, 1, 1, [None; 4]);
+    let lost = spawn(&mut state, EntityKind::Box, 5, 2);
+    let (state, _) = turn(&state, Input::Wait);
+    assert!(state.get(lost).is_none(), "boxes below the level are lost");
+    assert_eq!(state.failed, None);
+
+    let mut state = level(&hole);
+    let id = player(&mut state, 5, 2, [None; 4]);
+    let (state, history) = turn(&state, Input::Wait);
+    assert_eq!(history.len(), 3);
+    assert_eq!(pose(&state, id), (5, -1, 0));
+    assert_eq!(state.failed, Some(id));
+}
+
+#[test]
+fn spring_launches_back_by_the_fall_distance_once() {
+    let mut state = level(&[
+        "#######", //
+        "#.....#", //
+        "#.....#", //
+        "#.....#", //
+        "#.....#", //
+        "#.....#", //
+        "###=###", //
+    ]);
+    let id = player(&mut state, 3, 4, [None; 4]);
+    for _ in 0..3 {
+        (state, _) = step(&state, Input::Wait);
+    }
+    assert_eq!(pose(&state, id), (3, 1, 0));
+    assert_eq!(state.get(id).unwrap().fall_distance, 3);
+
+    let (bounced, events) = step(&state, Input::Wait);
+    assert_eq!(kinds(&events), [MoveKind::Bounce]);
+    assert_eq!(pose(&bounced, id), (3, 4, 0));
+    assert!(bounced.get(id).unwrap().bouncing);
+
+    // Falling back after the bounce does not count
+    let (state, history) = turn(&bounced, Input::Wait);
+    assert_eq!(kinds(&history.concat()), [MoveKind::Fall; 3]);
+    assert_eq!(pose(&state, id), (3, 1, 0));
+    assert_eq!(state.get(id).unwrap().fall_distance, 0);
+}
+
+#[test]
+fn one_way_cells_only_block_moving_down_into_them() {
+    let mut state = level(&[
+        "########", //
+        "#......#", //
+        "#......#", //
+        "#......#", //
+        "#...-..#", //
+        "#..#...#", //
+        "########", //
+    ]);
+    let id = player(&mut state, 3, 2, [None; 4]);
+    let landed = spawn(&mut state, EntityKind::Box, 4, 5);
+    let (state, _) = turn(&state, Input::Wait);
+    assert_eq!(pose(&state, landed), (4, 3, 0));
+
+    // Walking in from the side and falling out of it below the box
+    let (state, history) = turn(&state, Input::Move(Direction::Right));
+    assert_eq!(kinds(&history.concat()), [MoveKind::Walk, MoveKind::Fall]);
+    assert_eq!(pose(&state, id), (4, 1, 3));
+    assert_eq!(pose(&state, landed), (4, 3, 0));
+}
+
+#[test]
+fn canonical_form_ignores_full_turns_and_spawn_order() {
+    let mut state = level(&ROOM);
+    player(&mut state, 1, 1, [Some("Jump"), None, None, None]);
+    spawn(&mut state, EntityKind::Box, 3, 1);
+
+    let mut other = level(&ROOM);
+    let turned = spawn(&mut other, EntityKind::Box, 3, 1);
+    other.move_entity(turned, GridCoords::new(3, 1), Rotation(-4));
+    let id = player(&mut other, 1, 1, [Some("Jump"), None, None, None]);
+    other.move_entity(id, GridCoords::new(1, 1), Rotation(8));
+    assert_eq!(state.canonical(), other.canonical());
+    assert_eq!(
+        state.canonical().stable_hash(),
+        other.canonical().stable_hash()
+    );
+
+    other.move_entity(turned, GridCoords::new(4, 1), Rotation(-4));
+    assert_ne!(state.canonical(), other.canonical());
+    assert_ne!(
+        state.canonical().stable_hash(),
+        other.canonical().stable_hash()
+    );
+}
+
+#[test]
+fn going_back_to_a_snapshot_and_replaying_gives_the_same_turns() {
+    let mut state = level(&ROOM);
+    let id = player(&mut state, 1, 1, [None, None, None, Some("Punch")]);
+    let devnull = spawn(&mut state, EntityKind::DevNull, 2, 1);
+    let powerup = spawn(&mut state, EntityKind::Powerup(power("Slide")), 3, 1);
+    let mut snapshots = vec![state];
+    for _ in 0..2 {
+        let (state, _) = turn(snapshots.last().unwrap(), Input::Move(Direction::Right));
+        snapshots.push(state);
+    }
+    let last = &snapshots[2];
+    assert!(last.get(devnull).is_none() && last.get(powerup).is_none());
+    assert_eq!(
+        last.get(id).unwrap().sides,
+        [None, None, Some(power("Slide")), None]
+    );
+
+    // Undo to the start, everything is back
+    let first = &snapshots[0];
+    assert_eq!(pose(first, devnull), (2, 1, 0));
+    assert_eq!(pose(first, powerup), (3, 1, 0));
+    assert_eq!(first.get(id).unwrap().sides[3], Some(power("Punch")));
+    // Redo plays out exactly as before
+    for (before, after) in snapshots.iter().zip(&snapshots[1..]) {
+        let (state, _) = turn(before, Input::Move(Direction::Right));
+        assert_eq!(state, *after);
+        assert_eq!(state.canonical(), after.canonical());
+    }
+}
+
+#[test]
+fn inputs_parse_what_they_display() {
+    let inputs = [
+        Input::Move(Direction::Left),
+        Input::Move(Direction::None),
+        Input::Move(Direction::Right),
+        Input::SwitchPlayer(1),
+        Input::SwitchPlayer(-1),
+        Input::Wait,
+    ];
+    for input in inputs {
+        assert_eq!(input.to_string().parse(), Ok(input));
+    }
+    for text in ["up", "switch", "switch+x", ""] {
+        assert!(text.parse::<Input>().is_err(), "{text:?}");
+    }
+}
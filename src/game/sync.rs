@@ -0,0 +1,471 @@
+//! Glue between the ECS and the [sim]
+//!
+//! Every step the world is gathered into a [sim::LevelState], stepped,
+//! and everything except movement (which is animated from [turns::MoveEvent]s) is written back.
+
+use bevy::{
+    ecs::system::SystemParam,
+    utils::{HashMap, HashSet},
+};
+
+use super::{
+    conveyor::Conveyor,
+    goal::{Goal, Target},
+    grid::Grid,
+    level::{Blocking, LevelCell},
+    player::{Fall, Movable, OverrideGravity, Pushable, SelectedPlayer, SlideMove},
+    side::{
+        DevNull, Glued, InPortal, Portal, PowerQuery, Powerup, Side, SlideSfx, Trigger, Turned,
+        WallEffect,
+    },
+    signals::{Active, Signal},
+    *,
+};
+
+pub struct Plugin;
+
+impl bevy::app::Plugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.add_turn_system(turn_system, turns::TurnOrder::Simulate);
+    }
+}
+
+fn turn_system(
+    mut sim: SimWorld,
+    steps: Res<turns::TurnSteps>,
+    mut events: EventWriter<turns::MoveEvent>,
+) {
+    // Not stepping ends the turn, see [turns::TurnSteps]
+    if steps.keep_stepping() {
+        events.send_batch(sim.step(sim::Input::Wait));
+    }
+}
+
+/// Despawned in the [sim], but kept around hidden so that undo can bring it back
+///
+/// Holds the last state of the entity, what restoring it writes back is compared against it.
+#[derive(Component)]
+pub struct Despawned(sim::EntityState);
+
+#[derive(WorldQuery)]
+struct SimEntityQuery {
+    entity: Entity,
+    coords: &'static GridCoords,
+    rotation: Option<&'static Rotation>,
+    player: Option<&'static PlayerIndex>,
+    movable: Option<&'static Movable>,
+    pushable: Option<&'static Pushable>,
+    trigger: Option<&'static Trigger>,
+    powerup: Option<&'static Powerup>,
+    wall_effect: Option<&'static WallEffect>,
+    devnull: Option<&'static DevNull>,
+    goal: Option<&'static Goal>,
+    target: Option<&'static Target>,
+    portal: Option<&'static Portal>,
+    signal: Option<&'static Signal>,
+    conveyor: Option<&'static Conveyor>,
+    active: Option<&'static Active>,
+    power: PowerQuery,
+    input: Option<&'static player::Input>,
+    override_gravity: Option<&'static OverrideGravity>,
+    slide_move: Option<&'static SlideMove>,
+    slide_sfx: Option<&'static SlideSfx>,
+    push_motion: Option<&'static PushMotion>,
+    in_portal: Option<&'static InPortal>,
+    glued: Option<&'static Glued>,
+    turned: Option<&'static Turned>,
+    fall: Option<&'static Fall>,
+    selected: Option<&'static SelectedPlayer>,
+    children: Option<&'static Children>,
+}
+
+impl SimEntityQueryItem<'_> {
+    fn kind(&self) -> Option<sim::EntityKind> {
+        use sim::EntityKind::*;
+        Some(if let Some(index) = self.player {
+            Player(index.0)
+        } else if self.movable.is_some() {
+            match (self.pushable.is_some(), self.trigger.is_some()) {
+                (true, true) => Box,
+                (true, false) => DisableBox,
+                (false, _) => Crate,
+            }
+        } else if self.powerup.is_some() {
+            Powerup(self.power.power()?)
+        } else if self.wall_effect.is_some() {
+            WallEffect(self.power.power()?)
+        } else if self.devnull.is_some() {
+            DevNull
+        } else if let Some(goal) = self.goal {
+            Goal(goal.0)
+        } else if self.target.is_some() {
+            Target
+        } else if let Some(portal) = self.portal {
+            Portal(portal.pair)
+        } else if let Some(&signal) = self.signal {
+            signal.into()
+        } else if self.conveyor.is_some() {
+            Conveyor
+        } else {
+            return None;
+        })
+    }
+}
+
+/// Access to everything [sim::step] needs
+#[derive(SystemParam)]
+pub struct SimWorld<'w, 's> {
+    /// Level cells are taken from the [Grid] instead
+    entities: Query<'w, 's, SimEntityQuery, Without<LevelCell>>,
+    grid: Res<'w, Grid>,
+    sides: Query<'w, 's, (&'static Side, PowerQuery)>,
+    powerup_sprites: Query<
+        'w,
+        's,
+        (
+            &'static TextureAtlasSprite,
+            &'static Handle<TextureAtlas>,
+            PowerQuery,
+        ),
+        With<Powerup>,
+    >,
+    despawned: Query<'w, 's, &'static Despawned>,
+    levels: Query<'w, 's, &'static Handle<LdtkLevel>>,
+    level_assets: Res<'w, Assets<LdtkLevel>>,
+    failures: EventWriter<'w, turns::LevelFailed>,
+    completions: EventWriter<'w, turns::LevelCompleted>,
+    audio: Res<'w, Audio>,
+    audio_sinks: Res<'w, Assets<AudioSink>>,
+    asset_server: Res<'w, AssetServer>,
+    commands: Commands<'w, 's>,
+}
+
+impl SimWorld<'_, '_> {
+    /// Runs a [sim::step] on the current world, returning the moves to animate
+    pub fn step(&mut self, input: sim::Input) -> Vec<turns::MoveEvent> {
+        let (state, entities) = self.gather();
+        let (new_state, moves) = sim::step(&state, input);
+        if cfg!(debug_assertions) {
+            if let Err(error) = new_state.check_grid() {
+                error!("Grid is out of sync: {error}");
+            }
+        }
+        self.apply(&state, &new_state, &entities, &moves);
+        moves
+            .into_iter()
+            .map(|event| turns::MoveEvent {
+                player: entities[event.entity.0],
+                coords: event.coords,
+                rotation: event.rotation,
+                sfx: event.sfx,
+                end_sfx: event.end_sfx,
+                vfx: event.vfx.map(VfxBundle::from),
+                end_vfx: event.end_vfx.map(VfxBundle::from),
+                teleport: event.teleport,
+            })
+            .collect()
+    }
+
+    /// Selects another player through [sim::step], returns where the selected one is
+    pub fn switch_player(&mut self, dir: i32) -> Option<GridCoords> {
+        let (state, entities) = self.gather();
+        let (new_state, moves) = sim::step(&state, sim::Input::SwitchPlayer(dir));
+        self.apply(&state, &new_state, &entities, &moves);
+        Some(new_state.get(new_state.selected?)?.coords)
+    }
+
+    /// Builds the [sim::LevelState], entity at index `i` has [sim::EntityId] `i`
+    ///
+    /// Only entities are collected, the level cells are shared from the [Grid].
+    pub fn gather(&self) -> (sim::LevelState, Vec<Entity>) {
+        let mut state = sim::LevelState::default();
+        state.cells = self.grid.cells.clone();
+        if let Some(level) = self
+            .levels
+            .get_single()
+            .ok()
+            .and_then(|handle| self.level_assets.get(handle))
+        {
+            state.rules = sim::LevelRules::from_ldtk(&level.level)
+                .unwrap_or_else(|e| panic!("{}: {e}", level.level.identifier));
+        }
+        // Query order changes with the archetypes, moves are made in the order of ids
+        let mut items: Vec<_> = self.entities.iter().collect();
+        items.sort_by_key(|item| item.entity);
+        let mut entities = Vec::new();
+        for item in items {
+            let Some(kind) = item.kind() else {
+                continue;
+            };
+            let mut entity = sim::EntityState::new(
+                kind,
+                *item.coords,
+                item.rotation.copied().unwrap_or_default(),
+            );
+            for &child in item.children.iter().flat_map(|children| children.iter()) {
+                if let Ok((side, power)) = self.sides.get(child) {
+                    entity.sides[side.0 as usize] = power.power();
+                }
+            }
+            entity.input = item.input.map_or(Direction::None, |input| input.direction);
+            entity.override_gravity = item
+                .override_gravity
+                .map_or_else(Vec::new, |gravity| gravity.0.clone());
+            entity.slide_move = item.slide_move.is_some();
+            entity.sliding = item.slide_sfx.is_some();
+            entity.push_motion = item.push_motion.copied().unwrap_or_default();
+            entity.in_portal = item.in_portal.is_some();
+            entity.group = item.glued.map(|glued| glued.0);
+            entity.turned = item.turned.is_some();
+            entity.active = item.active.is_some();
+            if let Some(fall) = item.fall {
+                entity.fall_distance = fall.distance;
+                entity.bouncing = fall.bouncing;
+            }
+            let id = state.spawn(entity);
+            if item.selected.is_some() {
+                state.selected = Some(id);
+            }
+            entities.push(item.entity);
+        }
+        (state, entities)
+    }
+
+    fn side_entity(&self, entity: Entity, side: usize) -> Option<Entity> {
+        let children = self.entities.get(entity).ok()?.children?;
+        children.iter().copied().find(|&child| {
+            self.sides
+                .get(child)
+                .is_ok_and(|(child_side, _)| child_side.0 as usize == side)
+        })
+    }
+
+    /// Sprite for a side with given power, taken from a powerup of the same power
+    fn power_sprite(
+        &self,
+        power: sim::Power,
+    ) -> Option<(TextureAtlasSprite, Handle<TextureAtlas>)> {
+        self.powerup_sprites
+            .iter()
+            .find(|(.., powerup)| powerup.power() == Some(power))
+            .map(|(sprite, atlas, _)| (sprite.clone(), atlas.clone()))
+    }
+
+    fn despawn(&mut self, entity: Entity, last: &sim::EntityState) {
+        self.commands
+            .entity(entity)
+            .remove::<GridCoords>()
+            .insert((Despawned(last.clone()), Visibility::Hidden));
+    }
+
+    /// Writes back everything that changed, except for coords and rotations
+    fn apply(
+        &mut self,
+        old: &sim::LevelState,
+        new: &sim::LevelState,
+        entities: &[Entity],
+        moves: &[sim::MoveEvent],
+    ) {
+        for (id, old_entity) in old.iter() {
+            let entity = entities[id.0];
+            let Some(new_entity) = new.get(id) else {
+                self.despawn(entity, old_entity);
+                continue;
+            };
+
+            for side in 0..4 {
+                if old_entity.sides[side] != new_entity.sides[side] {
+                    self.audio.play_sfx(self.asset_server.load(
+                        if new_entity.sides[side].is_some() {
+                            "sfx/powerUp.wav"
+                        } else {
+                            "sfx/hitHurt.wav"
+                        },
+                    ));
+                }
+            }
+            if !new_entity.sliding
+                && old_entity.sliding
+                && !moves.iter().any(|event| event.entity == id)
+            {
+                self.audio
+                    .play_sfx(self.asset_server.load("sfx/hitWall.wav"));
+            }
+            self.write_entity(entity, old_entity, new_entity);
+        }
+
+        if let (Some(player), None) = (new.failed, old.failed) {
+            self.failures.send(turns::LevelFailed {
+                player: entities[player.0],
+            });
+        }
+
+        if new.completed && !old.completed {
+            self.completions.send(turns::LevelCompleted);
+        }
+
+        self.select(
+            old.selected.map(|id| entities[id.0]),
+            new.selected.map(|id| entities[id.0]),
+        );
+    }
+
+    /// Moves [SelectedPlayer] from one player to another
+    fn select(&mut self, old: Option<Entity>, new: Option<Entity>) {
+        if old == new {
+            return;
+        }
+        if let Some(entity) = old {
+            self.commands.entity(entity).remove::<SelectedPlayer>();
+        }
+        if let Some(entity) = new {
+            self.commands.entity(entity).insert(SelectedPlayer);
+        }
+    }
+
+    /// Writes back the changes of a single entity, except for coords and rotation
+    fn write_entity(&mut self, entity: Entity, old: &sim::EntityState, new: &sim::EntityState) {
+        for side in 0..4 {
+            let power = new.sides[side];
+            if old.sides[side] == power {
+                continue;
+            }
+            let Some(side_entity) = self.side_entity(entity, side) else {
+                continue;
+            };
+            let sprite = power.and_then(|power| self.power_sprite(power));
+            side::set_side_power(&mut self.commands, side_entity, power, sprite);
+        }
+
+        let mut commands = self.commands.entity(entity);
+        if new.input != old.input {
+            commands.insert(player::Input {
+                direction: new.input,
+            });
+        }
+        if new.slide_move != old.slide_move {
+            if new.slide_move {
+                commands.insert(SlideMove);
+            } else {
+                commands.remove::<SlideMove>();
+            }
+        }
+        if new.override_gravity != old.override_gravity {
+            if new.override_gravity.is_empty() {
+                commands.remove::<OverrideGravity>();
+            } else {
+                commands.insert(OverrideGravity(new.override_gravity.clone()));
+            }
+        }
+        if (new.fall_distance, new.bouncing) != (old.fall_distance, old.bouncing) {
+            if new.fall_distance == 0 && !new.bouncing {
+                commands.remove::<Fall>();
+            } else {
+                commands.insert(Fall {
+                    distance: new.fall_distance,
+                    bouncing: new.bouncing,
+                });
+            }
+        }
+        if new.in_portal != old.in_portal {
+            if new.in_portal {
+                commands.insert(InPortal);
+            } else {
+                commands.remove::<InPortal>();
+            }
+        }
+        if new.turned != old.turned {
+            if new.turned {
+                commands.insert(Turned);
+            } else {
+                commands.remove::<Turned>();
+            }
+        }
+        if new.active != old.active {
+            if new.active {
+                commands.insert(Active);
+            } else {
+                commands.remove::<Active>();
+            }
+            if matches!(new.kind, sim::EntityKind::Door(_)) {
+                if new.active {
+                    commands.remove::<Blocking>();
+                } else {
+                    commands.insert(Blocking);
+                }
+            }
+        }
+        if new.group != old.group {
+            match new.group {
+                Some(group) => commands.insert(Glued(group)),
+                None => commands.remove::<Glued>(),
+            };
+        }
+        if new.sliding && !old.sliding {
+            let sfx = self.audio.play_sfx(self.asset_server.load("sfx/slide.wav"));
+            commands.insert(SlideSfx(self.audio_sinks.get_handle(sfx)));
+        } else if !new.sliding && old.sliding {
+            commands.remove::<SlideSfx>();
+            if let Some(sfx) = self
+                .entities
+                .get(entity)
+                .ok()
+                .and_then(|item| item.slide_sfx)
+            {
+                if let Some(sink) = self.audio_sinks.get(&sfx.0) {
+                    sink.stop();
+                }
+            }
+        }
+    }
+
+    /// Puts the world back into a [gathered](Self::gather) state, coords and rotations included
+    ///
+    /// Transforms are left to the caller, see [history](super::history).
+    pub fn restore(&mut self, state: &sim::LevelState, entities: &[Entity]) {
+        let (current, current_entities) = self.gather();
+        let current_ids: HashMap<Entity, sim::EntityId> = current_entities
+            .iter()
+            .enumerate()
+            .map(|(index, &entity)| (entity, sim::EntityId(index)))
+            .collect();
+        let restored: HashSet<Entity> = state.iter().map(|(id, _)| entities[id.0]).collect();
+
+        for (id, current_entity) in current.iter() {
+            let entity = current_entities[id.0];
+            if !restored.contains(&entity) {
+                self.despawn(entity, current_entity);
+            }
+        }
+        for (id, new_entity) in state.iter() {
+            let entity = entities[id.0];
+            let old_entity = match current_ids.get(&entity).and_then(|&id| current.get(id)) {
+                Some(old_entity) => old_entity.clone(),
+                None => {
+                    // Picked up, fallen out of the level or the like, it may have moved and turned since
+                    let Ok(Despawned(last)) = self.despawned.get(entity) else {
+                        continue;
+                    };
+                    let last = last.clone();
+                    self.commands
+                        .entity(entity)
+                        .remove::<Despawned>()
+                        .insert((new_entity.coords, Visibility::Inherited));
+                    last
+                }
+            };
+            if new_entity.coords != old_entity.coords {
+                self.commands.entity(entity).insert(new_entity.coords);
+            }
+            if new_entity.rotation != old_entity.rotation {
+                self.commands.entity(entity).insert(new_entity.rotation);
+            }
+            self.write_entity(entity, &old_entity, new_entity);
+        }
+
+        self.select(
+            current.selected.map(|id| current_entities[id.0]),
+            state.selected.map(|id| entities[id.0]),
+        );
+    }
+}
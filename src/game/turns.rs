@@ -5,13 +5,7 @@ pub struct Plugin;
 impl bevy::app::Plugin for Plugin {
     fn build(&self, app: &mut App) {
         app.add_state::<State>();
-
-        app.configure_set(TurnOrder::CollectPowerups.before(TurnOrder::ApplyBuffers1));
-        app.configure_set(TurnOrder::DetectSideEffect.after(TurnOrder::ApplyBuffers1));
-        app.configure_set(TurnOrder::DetectSideEffect.before(TurnOrder::ApplyBuffers2));
-        app.configure_set(TurnOrder::ApplySideEffects.after(TurnOrder::ApplyBuffers2));
-        app.add_system(apply_system_buffers.in_set(TurnOrder::ApplyBuffers1));
-        app.add_system(apply_system_buffers.in_set(TurnOrder::ApplyBuffers2));
+        app.init_resource::<TurnSteps>();
 
         app.add_system(loading_level_finish);
         app.add_systems(
@@ -24,16 +18,15 @@ impl bevy::app::Plugin for Plugin {
         app.add_system(process_animation.in_set(OnUpdate(State::Animation)));
 
         app.add_event::<MoveEvent>();
+        app.add_event::<LevelFailed>();
+        app.add_event::<LevelCompleted>();
     }
 }
 
 #[derive(SystemSet, Hash, PartialEq, Eq, Debug, Copy, Clone)]
 pub enum TurnOrder {
-    CollectPowerups,
-    ApplyBuffers1,
-    DetectSideEffect,
-    ApplyBuffers2,
-    ApplySideEffects,
+    /// Runs [sim::step], the whole turn logic is there
+    Simulate,
 }
 
 pub struct MoveEvent {
@@ -44,8 +37,34 @@ pub struct MoveEvent {
     pub end_sfx: Option<&'static str>,
     pub vfx: Option<VfxBundle>,
     pub end_vfx: Option<VfxBundle>,
+    /// Disappears and appears at `coords` instead of moving there
+    pub teleport: bool,
 }
 
+/// A player touched a hazard or fell out of the level, see [sim::LevelState::failed]
+pub struct LevelFailed {
+    pub player: Entity,
+}
+
+/// The objective of the level is met, see [sim::LevelState::completed]
+///
+/// Sent at most once per turn, the turn ends right away and the next level is loaded.
+pub struct LevelCompleted;
+
+/// Steps played since the last input
+///
+/// Entities that never settle, e.g. on conveyors facing each other, would otherwise block the input.
+#[derive(Resource, Default)]
+pub struct TurnSteps(usize);
+
+impl TurnSteps {
+    /// Whether another [sim::Input::Wait] step should be played, see [sim::MAX_SETTLE_STEPS]
+    pub fn keep_stepping(&self) -> bool {
+        self.0 < sim::MAX_SETTLE_STEPS
+    }
+}
+
+/// Time since the animation started, the turn ends when the longest [Animation](animation::Animation) does
 #[derive(Resource)]
 pub struct AnimationTimer(Timer);
 
@@ -53,9 +72,8 @@ impl AnimationTimer {
     pub fn new(animation_time_seconds: f32) -> Self {
         Self(Timer::from_seconds(animation_time_seconds, TimerMode::Once))
     }
-    /// Returns value from 0 (start of animation) to 1 (end of animation)
-    pub fn progress(&self) -> f32 {
-        self.0.elapsed_secs() / self.0.duration().as_secs_f32()
+    pub fn elapsed_secs(&self) -> f32 {
+        self.0.elapsed_secs()
     }
 }
 
@@ -84,13 +102,17 @@ pub enum State {
     Turn,
     WaitingForInput,
     Animation,
+    /// The level can not be completed anymore, waiting for an undo or a restart
+    Failed,
 }
 
 fn loading_level_finish(
     mut next_state: ResMut<NextState<State>>,
+    mut steps: ResMut<TurnSteps>,
     query: Query<(), Added<Handle<LdtkLevel>>>,
 ) {
     if !query.is_empty() {
+        steps.0 = 0;
         next_state.set(State::Turn);
     }
 }
@@ -110,12 +132,34 @@ fn stop_animation(mut next_state: ResMut<NextState<State>>, turn_timer: Res<Anim
 /// This is here just for the sake of ordering
 fn start_turn() {}
 
-fn end_turn(mut next_state: ResMut<NextState<State>>, events: EventReader<MoveEvent>) {
-    // No events means no animation to play so we wait for player input
-    if events.is_empty() {
+fn end_turn(
+    mut next_state: ResMut<NextState<State>>,
+    mut steps: ResMut<TurnSteps>,
+    events: EventReader<MoveEvent>,
+    mut failures: EventReader<LevelFailed>,
+    mut completions: EventReader<LevelCompleted>,
+) {
+    if !completions.is_empty() {
+        // Stepping on would complete the level again, skipping the next one
+        completions.clear();
+        info!("Level completed");
+        steps.0 = 0;
+        next_state.set(State::LoadingLevel);
+    } else if !failures.is_empty() {
+        failures.clear();
+        info!("Level failed");
+        steps.0 = 0;
+        next_state.set(State::Failed);
+    } else if events.is_empty() {
+        // No events means no animation to play so we wait for player input
+        if !steps.keep_stepping() {
+            warn!("Entities did not settle, waiting for input anyway");
+        }
         info!("Waiting for input now");
+        steps.0 = 0;
         next_state.set(State::WaitingForInput);
     } else {
+        steps.0 += 1;
         next_state.set(State::Animation);
     }
 }
@@ -1,6 +1,10 @@
+use serde::{Deserialize, Serialize};
+
 use super::*;
 
-#[derive(Default, Clone, Copy, PartialEq, Eq)]
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
 pub enum Direction {
     Left,
     #[default]
@@ -29,7 +33,7 @@ impl std::ops::Neg for Direction {
     }
 }
 
-#[derive(Debug, Default, Component, Clone, Copy)]
+#[derive(Debug, Default, Component, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Rotation(pub i32);
 
 impl From<&ldtk::EntityInstance> for Rotation {
@@ -59,6 +63,10 @@ impl Rotation {
     pub fn to_radians(self) -> f32 {
         self.0 as f32 * PI / 2.0
     }
+    /// Same rotation in `0..4`, since rolling makes it grow without bound
+    pub fn normalized(self) -> Self {
+        Self(self.0.rem_euclid(4))
+    }
     pub fn rotate_right(&mut self) {
         self.0 -= 1;
     }
@@ -77,6 +85,47 @@ impl Rotation {
     }
 }
 
+/// How a pushable entity moves when pushed sideways
+#[derive(
+    Debug,
+    Default,
+    Component,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+)]
+pub enum PushMotion {
+    /// Rotates around the edge it stands on, turning its sides with it
+    #[default]
+    Roll,
+    /// Keeps its rotation
+    Slide,
+}
+
+impl From<&ldtk::EntityInstance> for PushMotion {
+    fn from(entity: &ldtk::EntityInstance) -> Self {
+        entity
+            .field_instances
+            .iter()
+            .find(|field| field.identifier == "PushMotion")
+            .map(|field| match &field.value {
+                FieldValue::Enum(value) => match value.as_deref() {
+                    None | Some("Roll") => Self::Roll,
+                    Some("Slide") => Self::Slide,
+                    Some(other) => panic!("Unexpected value for push motion: {other:?}"),
+                },
+                _ => panic!("PushMotion should be enum"),
+            })
+            .unwrap_or_default()
+    }
+}
+
 pub fn vec_to_rot(v: IVec2) -> i32 {
     if v.y < 0 {
         return 0;
@@ -113,21 +162,3 @@ pub fn player_side(player_rot: &Rotation, dir: IVec2) -> i32 {
     // player_rot - side_rot = dir
     player_rot - dir
 }
-
-/// This is a helper to see if a cell is blocked or not
-/// add blocked: Query<BlockedQuery, With<Blocking>> to system, then us is_blocked(coords, &blocked)
-///
-/// This should probably be done instead by introducing a resource that maintains a grid
-/// For faster access, and having systems in place to synchronize it
-#[derive(WorldQuery)]
-pub struct BlockedQuery {
-    coords: &'static GridCoords,
-}
-
-pub fn is_blocked(
-    coords: GridCoords,
-    query: &Query<BlockedQuery, impl ReadOnlyWorldQuery>,
-) -> bool {
-    // TODO: bad performance
-    query.iter().any(|item| item.coords == &coords)
-}
@@ -12,26 +12,45 @@ impl bevy::app::Plugin for Plugin {
 #[derive(Bundle, Clone)]
 pub struct VfxBundle {
     atlas_path: AtlasPath,
+    coords: VfxCoords,
     sprite_sheet: SpriteSheetBundle,
     animation_timer: AnimationTimer,
     indices: AnimationIndices,
 }
 
+/// Atlas and the size of its frames, a tile if not set
 #[derive(Component, Clone)]
-struct AtlasPath(&'static str, f32);
+struct AtlasPath(&'static str, Option<f32>);
+
+/// Cell the effect is played at, placed once the tile size is known
+#[derive(Component, Clone)]
+struct VfxCoords(GridCoords);
+
+/// Effect that still needs its atlas loaded and its position set
+type NewVfx = (
+    Entity,
+    &'static mut Handle<TextureAtlas>,
+    &'static mut Transform,
+    &'static AtlasPath,
+    &'static VfxCoords,
+);
 
 fn load_atlas(
     levels: Query<Entity, With<Handle<LdtkLevel>>>,
-    mut query: Query<(Entity, &mut Handle<TextureAtlas>, &AtlasPath), Added<AtlasPath>>,
+    mut query: Query<NewVfx, Added<AtlasPath>>,
+    tile_size: Res<level::TileSize>,
     asset_server: Res<AssetServer>,
     mut texture_atlases: ResMut<Assets<TextureAtlas>>,
     mut commands: Commands,
 ) {
-    for (entity, mut handle, path) in query.iter_mut() {
+    for (entity, mut handle, mut transform, path, coords) in query.iter_mut() {
         let texture_handle = asset_server.load(path.0);
+        let frame_size = path.1.unwrap_or(tile_size.x as f32);
         let texture_atlas =
-            TextureAtlas::from_grid(texture_handle, Vec2::new(path.1, path.1), 1, 5, None, None);
+            TextureAtlas::from_grid(texture_handle, Vec2::splat(frame_size), 1, 5, None, None);
         *handle = texture_atlases.add(texture_atlas);
+        transform.translation =
+            grid_coords_to_translation(coords.0, **tile_size).extend(transform.translation.z);
         if let Ok(level) = levels.get_single() {
             commands.entity(level).add_child(entity);
         }
@@ -49,20 +68,23 @@ impl VfxBundle {
     ) -> Self {
         const FRAMES: usize = 5;
         Self {
-            atlas_path: AtlasPath(atlas, atlas_size.unwrap_or(16.0)),
+            atlas_path: AtlasPath(atlas, atlas_size),
+            coords: VfxCoords(coords),
             sprite_sheet: SpriteSheetBundle {
                 sprite: TextureAtlasSprite {
                     flip_x: mirror,
                     ..default()
                 },
                 transform: Transform::from_rotation(Quat::from_rotation_z(rot as f32 * PI / 2.0))
-                    .with_translation(
-                        grid_coords_to_translation(coords, IVec2::new(16, 16)).extend(if top {
+                    .with_translation(Vec3::new(
+                        0.0,
+                        0.0,
+                        if top {
                             234.5
                         } else {
                             23.5 // KEKW
-                        }),
-                    ),
+                        },
+                    )),
                 ..default()
             },
             indices: AnimationIndices {
@@ -77,6 +99,19 @@ impl VfxBundle {
     }
 }
 
+impl From<sim::Vfx> for VfxBundle {
+    fn from(vfx: sim::Vfx) -> Self {
+        Self::new(
+            vfx.coords,
+            vfx.rot,
+            vfx.atlas,
+            vfx.atlas_size,
+            vfx.top,
+            vfx.mirror,
+        )
+    }
+}
+
 #[derive(Debug, Component, Clone)]
 struct AnimationIndices {
     #[allow(dead_code)] // LOL
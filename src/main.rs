@@ -3,8 +3,7 @@
 
 use bevy::{diagnostic::LogDiagnosticsPlugin, prelude::*};
 use bevy_ecs_ldtk::prelude::*;
-
-mod game;
+use linksider::game;
 
 // BEVY IS GARBAGE, RUST IS GARBAGE, BADCOP_ IS THE BEST
 // I didn't write that comment ^ - @badcop_
@@ -36,6 +35,9 @@ fn main() {
     .add_plugin(LdtkPlugin) // Ldtk is our level editor
     .add_plugin(game::Plugin);
 
+    app.insert_resource(game::replay::Mode::from_args(std::env::args().skip(1)));
+
+    #[allow(clippy::overly_complex_bool_expr)] // Inspector is disabled for now
     if false && cfg!(debug_assertions) {
         app.add_plugin(LogDiagnosticsPlugin::default())
             // .add_plugin(FrameTimeDiagnosticsPlugin::default()) // This reports FPS to console
@@ -0,0 +1,156 @@
+//! Plays the stored solution of every level through the whole game, without a window
+//!
+//! Solutions are stored in the format of the `solve` report, to update them run
+//! `cargo run --release --bin solve -- --all --report tests/solutions.json`.
+//! The solver gives up on `Magnet_sidejump_combo` and `Magnet_coop_complex` before finding the
+//! shortest solution, theirs were found by a search that goes towards the goal first,
+//! so keep them when updating.
+
+use std::{
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use bevy::{
+    log::LogPlugin, prelude::*, render::pipelined_rendering::PipelinedRenderingPlugin,
+    time::TimeUpdateStrategy, winit::WinitPlugin,
+};
+use bevy_ecs_ldtk::prelude::*;
+use linksider::game::{
+    replay::{Action, Mode, Replay, ReplayInput},
+    sim, HeadlessPlugin,
+};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+struct Report {
+    levels: Vec<LevelReport>,
+}
+
+#[derive(Deserialize)]
+struct LevelReport {
+    number: usize,
+    identifier: String,
+    status: String,
+    solution: Option<Vec<String>>,
+}
+
+/// Levels without a goal, there is nothing to solve
+const WITHOUT_GOAL: [&str; 2] = ["Level_0", "GG"];
+
+/// Levels the game can not complete as it is, the solver went through every state they can reach
+const UNSOLVABLE: [&str; 8] = [
+    "MultipleSingleJump",
+    "SlideThroughJump",
+    "Walljump_intro",
+    "WallJump_rerot",
+    "Disable_fallsidejump_intro",
+    "Complex_Jump_two_char",
+    "Mega_complex_walljump_coop",
+    "Secret_AirJump",
+];
+
+/// Gives up on a level after this long, loading included
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+fn headless_app(level_index: usize, replay: &Path) -> App {
+    let mut app = App::new();
+    app.add_plugins(
+        DefaultPlugins
+            .build()
+            .disable::<WinitPlugin>()
+            .disable::<LogPlugin>()
+            // Without a window there is nothing to render in parallel with
+            .disable::<PipelinedRenderingPlugin>(),
+    )
+    .add_plugin(LdtkPlugin)
+    .add_plugin(HeadlessPlugin);
+
+    app.insert_resource(TimeUpdateStrategy::ManualInstant(Instant::now()));
+    app.insert_resource(LevelSelection::Index(level_index));
+    app.insert_resource(Mode::Play(replay.to_owned()));
+    app
+}
+
+/// Runs a frame that takes a whole second, so that every animation finishes in a single frame
+fn update(app: &mut App) {
+    if let TimeUpdateStrategy::ManualInstant(instant) = *app.world.resource::<TimeUpdateStrategy>()
+    {
+        app.insert_resource(TimeUpdateStrategy::ManualInstant(
+            instant + Duration::from_secs(1),
+        ));
+    }
+    app.update();
+}
+
+/// Frames played after the level changed, so that completing it twice would show up
+const FRAMES_AFTER_COMPLETION: usize = 10;
+
+/// Feeds the solution through a replay, returns the level selected in the end
+fn play_solution(index: usize, identifier: &str, solution: &[sim::Input]) -> LevelSelection {
+    let mut turn = 0;
+    let mut frame_turn = 0;
+    let mut replay = Replay::default();
+    for &input in solution {
+        // A switch is pressed on the frame of the move before it
+        if let sim::Input::Move(_) = input {
+            frame_turn = turn;
+        }
+        replay.inputs.push(ReplayInput {
+            level: identifier.to_owned(),
+            turn: frame_turn,
+            action: Action::Input(input),
+        });
+        if matches!(input, sim::Input::Move(direction) if direction != sim::Direction::None) {
+            turn += 1;
+        }
+    }
+    let path = std::env::temp_dir().join(format!("linksider-{identifier}.json"));
+    replay.save(&path).unwrap();
+
+    let mut app = headless_app(index, &path);
+    let start = Instant::now();
+    while start.elapsed() < TIMEOUT
+        && *app.world.resource::<LevelSelection>() == LevelSelection::Index(index)
+    {
+        update(&mut app);
+    }
+    for _ in 0..FRAMES_AFTER_COMPLETION {
+        update(&mut app);
+    }
+    std::fs::remove_file(&path).ok();
+    app.world.resource::<LevelSelection>().clone()
+}
+
+#[test]
+fn solutions_complete_levels() {
+    let report: Report = serde_json::from_str(include_str!("solutions.json")).unwrap();
+    let mut failed = Vec::new();
+    let mut missing = Vec::new();
+    for level in report.levels {
+        let identifier = level.identifier.as_str();
+        if UNSOLVABLE.contains(&identifier) {
+            assert_eq!(
+                level.status, "unsolvable",
+                "{identifier} was expected to be unsolvable"
+            );
+            continue;
+        }
+        let Some(solution) = level.solution else {
+            if !WITHOUT_GOAL.contains(&identifier) {
+                missing.push(level.identifier);
+            }
+            continue;
+        };
+        let solution: Vec<sim::Input> = solution
+            .iter()
+            .map(|input| input.parse().unwrap())
+            .collect();
+        let index = level.number - 1;
+        if play_solution(index, &level.identifier, &solution) != LevelSelection::Index(index + 1) {
+            failed.push(level.identifier);
+        }
+    }
+    assert!(missing.is_empty(), "No solution stored for {missing:?}");
+    assert!(failed.is_empty(), "Solutions did not complete {failed:?}");
+}
@@ -0,0 +1,41 @@
+//! Replay files load back what was saved
+
+use linksider::game::{
+    replay::{Action, Replay, ReplayInput},
+    sim::{Direction, Input},
+};
+
+#[test]
+fn replays_load_what_was_saved() {
+    let actions = [
+        Action::Input(Input::Move(Direction::Right)),
+        Action::Input(Input::SwitchPlayer(1)),
+        Action::Input(Input::Move(Direction::None)),
+        Action::Undo,
+        Action::Redo,
+        Action::Input(Input::SwitchPlayer(-1)),
+    ];
+    let replay = Replay {
+        inputs: actions
+            .into_iter()
+            .enumerate()
+            .map(|(turn, action)| ReplayInput {
+                level: "Level_1".to_owned(),
+                turn: turn as u32 / 2,
+                action,
+            })
+            .collect(),
+        ..Replay::default()
+    };
+    let path = std::env::temp_dir().join("linksider-replay-round-trip.json");
+    replay.save(&path).unwrap();
+    assert_eq!(Replay::load(&path), Ok(replay.clone()));
+
+    let other_version = Replay {
+        version: Replay::VERSION + 1,
+        ..replay
+    };
+    other_version.save(&path).unwrap();
+    assert!(Replay::load(&path).is_err());
+    std::fs::remove_file(&path).ok();
+}